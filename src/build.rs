@@ -2,7 +2,7 @@
 
 mod builder;
 
-use super::{Document, utility::is_broken};
+use super::{ColumnAlignment, Document, utility::is_broken};
 pub use builder::Builder;
 
 /// Creates a sequence of documents.
@@ -10,11 +10,58 @@ pub const fn sequence<'a>(documents: &'a [Document<'a>]) -> Document<'a> {
     Document::Sequence(documents)
 }
 
+/// Aligns a document by a number of spaces.
+pub const fn align<'a>(alignment: usize, document: &'a Document<'a>) -> Document<'a> {
+    Document::Align(alignment, document)
+}
+
+/// Aligns line suffixes in a document.
+pub const fn aligned_line_suffixes<'a>(document: &'a Document<'a>) -> Document<'a> {
+    Document::AlignedLineSuffixes(document)
+}
+
 /// Creates a line suffix.
 pub const fn line_suffix(string: &str) -> Document<'_> {
     Document::LineSuffix(string)
 }
 
+/// Caches if a document is broken or not.
+///
+/// It makes [`flatten_if`] and [`is_broken`] constant time on the document.
+pub fn cache<'a>(document: &'a Document<'a>) -> Document<'a> {
+    Document::Cache {
+        broken: is_broken(document),
+        document,
+    }
+}
+
+/// Creates a cursor.
+pub const fn cursor() -> Document<'static> {
+    Document::Cursor
+}
+
+/// Creates a fill of contents and separators alternating with each other.
+pub const fn fill<'a>(documents: &'a [Document<'a>]) -> Document<'a> {
+    Document::Fill(documents)
+}
+
+/// Creates a line suffix boundary.
+pub const fn line_suffix_boundary() -> Document<'static> {
+    Document::LineSuffixBoundary
+}
+
+/// Dedents a document by a level.
+pub const fn dedent<'a>(document: &'a Document<'a>) -> Document<'a> {
+    Document::Dedent(document)
+}
+
+/// Dedents a document to a root.
+///
+/// If `offside` is true, the root is the innermost [`offside`] document.
+pub const fn dedent_to_root<'a>(document: &'a Document<'a>, offside: bool) -> Document<'a> {
+    Document::DedentToRoot { document, offside }
+}
+
 /// Flattens a document.
 pub const fn flatten<'a>(document: &'a Document<'a>) -> Document<'a> {
     Document::Break {
@@ -39,6 +86,18 @@ pub fn flatten_if<'a>(condition: bool, document: &'a Document<'a>) -> Document<'
     }
 }
 
+/// Creates a group of documents broken into multiple lines only if it does
+/// not fit in a line.
+pub const fn group<'a>(document: &'a Document<'a>) -> Document<'a> {
+    Document::Group(document)
+}
+
+/// Creates a document rendered as `broken` if its parent document is broken or
+/// as `flat` otherwise.
+pub const fn if_break<'a>(broken: &'a Document<'a>, flat: &'a Document<'a>) -> Document<'a> {
+    Document::IfBreak { broken, flat }
+}
+
 /// Indents a document.
 pub const fn indent<'a>(document: &'a Document<'a>) -> Document<'a> {
     Document::Indent(document)
@@ -49,11 +108,46 @@ pub const fn line() -> Document<'static> {
     Document::Line
 }
 
+/// Creates a new line rendered even if it is not broken.
+pub const fn hard_line() -> Document<'static> {
+    Document::HardLine
+}
+
+/// Creates a new line rendered as nothing if it is not broken.
+pub const fn soft_line() -> Document<'static> {
+    Document::SoftLine
+}
+
+/// Creates a mark of a position.
+pub const fn mark(id: usize) -> Document<'static> {
+    Document::Mark(id)
+}
+
 /// Creates an empty document.
 pub const fn empty() -> Document<'static> {
     Document::String("")
 }
 
+/// Prefixes lines in a document.
+///
+/// The prefix is not rendered at a current line.
+pub const fn prefix<'a>(prefix: &'a str, document: &'a Document<'a>) -> Document<'a> {
+    Document::Prefix(prefix, document)
+}
+
+/// Creates a table of documents.
+pub const fn table<'a>(
+    rows: &'a [&'a [Document<'a>]],
+    alignments: &'a [ColumnAlignment],
+) -> Document<'a> {
+    Document::Table { rows, alignments }
+}
+
+/// Creates a verbatim string possibly with newlines.
+pub const fn verbatim(string: &str, indent: bool) -> Document<'_> {
+    Document::Verbatim { string, indent }
+}
+
 /// Creates a document indented to a current column.
 pub const fn offside<'a>(document: &'a Document<'a>, soft: bool) -> Document<'a> {
     Document::Offside { document, soft }
@@ -1,4 +1,8 @@
-use super::{Document, r#break, flatten, indent, line_suffix, offside, sequence};
+use super::{
+    ColumnAlignment, Document, align, aligned_line_suffixes, r#break, cache, dedent,
+    dedent_to_root, fill, flatten, flatten_if, group, if_break, indent, line_suffix, offside,
+    prefix, sequence, table, verbatim,
+};
 use allocator_api2::{alloc::Allocator, boxed::Box, vec::Vec};
 use core::str;
 
@@ -29,16 +33,75 @@ impl<'a, A: Allocator + Clone + 'a> Builder<A> {
         flatten(self.allocate(value.into()))
     }
 
+    /// Creates a group of documents.
+    ///
+    /// It caches if the document is broken or not.
+    pub fn group(&self, value: impl Into<Document<'a>>) -> Document<'a> {
+        group(self.allocate(self.cache(value)))
+    }
+
+    /// Creates a document rendered differently depending on if its parent
+    /// document is broken or not.
+    pub fn if_break(
+        &self,
+        broken: impl Into<Document<'a>>,
+        flat: impl Into<Document<'a>>,
+    ) -> Document<'a> {
+        if_break(self.allocate(broken.into()), self.allocate(flat.into()))
+    }
+
+    /// Flattens a document if a `condition` is true.
+    ///
+    /// Unlike [`flatten_if`](crate::flatten_if), it caches if the document is
+    /// broken or not.
+    pub fn flatten_if(&self, condition: bool, value: impl Into<Document<'a>>) -> Document<'a> {
+        flatten_if(condition, self.allocate(self.cache(value)))
+    }
+
+    /// Caches if a document is broken or not.
+    pub fn cache(&self, value: impl Into<Document<'a>>) -> Document<'a> {
+        cache(self.allocate(value.into()))
+    }
+
+    /// Dedents a document by a level.
+    pub fn dedent(&self, value: impl Into<Document<'a>>) -> Document<'a> {
+        dedent(self.allocate(value.into()))
+    }
+
+    /// Dedents a document to a root.
+    pub fn dedent_to_root(&self, value: impl Into<Document<'a>>, offside: bool) -> Document<'a> {
+        dedent_to_root(self.allocate(value.into()), offside)
+    }
+
     /// Indents a document by a level.
     pub fn indent(&self, value: impl Into<Document<'a>>) -> Document<'a> {
         indent(self.allocate(value.into()))
     }
 
+    /// Aligns a document by a number of spaces.
+    pub fn align(&self, alignment: usize, value: impl Into<Document<'a>>) -> Document<'a> {
+        align(alignment, self.allocate(value.into()))
+    }
+
+    /// Aligns line suffixes in a document.
+    pub fn aligned_line_suffixes(&self, value: impl Into<Document<'a>>) -> Document<'a> {
+        aligned_line_suffixes(self.allocate(value.into()))
+    }
+
     /// Creates a document indented to a current column.
     pub fn offside(&self, value: impl Into<Document<'a>>, soft: bool) -> Document<'a> {
         offside(self.allocate(value.into()), soft)
     }
 
+    /// Prefixes lines in a document.
+    pub fn prefix<'b>(
+        &self,
+        values: impl IntoIterator<Item = &'b str>,
+        value: impl Into<Document<'a>>,
+    ) -> Document<'a> {
+        prefix(self.allocate_str(values), self.allocate(value.into()))
+    }
+
     /// Creates a sequence of documents.
     pub fn sequence(
         &self,
@@ -47,6 +110,26 @@ impl<'a, A: Allocator + Clone + 'a> Builder<A> {
         sequence(self.allocate_slice(values.into_iter().map(Into::into)))
     }
 
+    /// Creates a fill of contents and separators alternating with each other.
+    pub fn fill(&self, values: impl IntoIterator<Item = impl Into<Document<'a>>>) -> Document<'a> {
+        fill(self.allocate_slice(values.into_iter().map(Into::into)))
+    }
+
+    /// Creates a table of documents.
+    pub fn table(
+        &self,
+        rows: impl IntoIterator<Item = impl IntoIterator<Item = impl Into<Document<'a>>>>,
+        alignments: impl IntoIterator<Item = ColumnAlignment>,
+    ) -> Document<'a> {
+        table(
+            self.allocate_slice(
+                rows.into_iter()
+                    .map(|row| self.allocate_slice(row.into_iter().map(Into::into))),
+            ),
+            self.allocate_slice(alignments),
+        )
+    }
+
     /// Creates a concatenation of strings.
     pub fn strings<'b>(&self, values: impl IntoIterator<Item = &'b str>) -> Document<'a> {
         self.allocate_str(values).into()
@@ -57,6 +140,15 @@ impl<'a, A: Allocator + Clone + 'a> Builder<A> {
         line_suffix(self.allocate_str(values))
     }
 
+    /// Creates a verbatim string possibly with newlines.
+    pub fn verbatim<'b>(
+        &self,
+        values: impl IntoIterator<Item = &'b str>,
+        indent: bool,
+    ) -> Document<'a> {
+        verbatim(self.allocate_str(values), indent)
+    }
+
     /// Allocates a value.
     pub fn allocate<T>(&self, value: T) -> &'a T {
         Box::leak(Box::new_in(value, self.allocator.clone()))
@@ -86,8 +178,9 @@ impl<'a, A: Allocator + Clone + 'a> Builder<A> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::offside;
+    use crate::{hard_line, if_break, line, offside, utility::is_broken};
     use allocator_api2::alloc::Global;
+    use bumpalo::Bump;
 
     #[test]
     fn build_offside() {
@@ -95,4 +188,55 @@ mod tests {
 
         assert_eq!(builder.offside("foo", false), offside(&"foo".into(), false));
     }
+
+    #[test]
+    fn build_align() {
+        let builder = Builder::new(Global);
+
+        assert_eq!(builder.align(3, "foo"), align(3, &"foo".into()));
+    }
+
+    #[test]
+    fn build_table() {
+        let builder = Builder::new(Global);
+
+        assert_eq!(
+            builder.table([["foo", "bar"]], [ColumnAlignment::Right]),
+            table(&[&["foo".into(), "bar".into()]], &[ColumnAlignment::Right])
+        );
+    }
+
+    #[test]
+    fn build_flatten_if() {
+        let builder = Builder::new(Global);
+
+        assert_eq!(
+            builder.flatten_if(true, "foo"),
+            flatten(&cache(&"foo".into()))
+        );
+        assert_eq!(
+            builder.flatten_if(true, hard_line()),
+            r#break(&cache(&hard_line()))
+        );
+    }
+
+    #[test]
+    fn build_deep_flatten_if() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut document = "foo".into();
+
+        for _ in 0..100_000 {
+            document = builder.flatten_if(true, builder.sequence([document, line()]));
+        }
+
+        assert!(!is_broken(&document));
+    }
+
+    #[test]
+    fn build_if_break() {
+        let builder = Builder::new(Global);
+
+        assert_eq!(builder.if_break(",", ""), if_break(&",".into(), &"".into()));
+    }
 }
@@ -0,0 +1,9 @@
+/// An alignment of cells in a table column.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColumnAlignment {
+    /// Cells padded with spaces on their right sides.
+    #[default]
+    Left,
+    /// Cells padded with spaces on their left sides.
+    Right,
+}
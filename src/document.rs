@@ -1,24 +1,81 @@
 // https://homepages.inf.ed.ac.uk/wadler/papers/prettier/prettier.pdf
 //
 // Unlike the Wadler's algorithm or some other formatters like prettier, we do
-// not need to search the best format given source codes. For example, we do
-// not have any "group" combinator.
+// not need to search the best format given source codes in most cases.
 //
 // However, we are rather given the "best" format by all information available
-// in the source codes like Go.
+// in the source codes like Go. Only groups break documents into multiple lines
+// depending on a maximum line width.
 //
 // We need soft-line and if-break nodes to make nodes totally agnostic about if
 // parent nodes are broken or not. But that also makes IR more complex.
 // (e.g. handling trailing commas in function calls)
 
+use crate::ColumnAlignment;
+
 /// A document.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Document<'a> {
+    /// A document aligned by a number of spaces.
+    ///
+    /// A formatter renders the spaces after an indent. They are always spaces
+    /// even if the indent consists of tabs.
+    Align(usize, &'a Document<'a>),
+    /// A document with aligned line suffixes.
+    ///
+    /// A formatter pads line suffixes on lines of code in the document with
+    /// spaces so that they start at the same column after the longest line.
+    AlignedLineSuffixes(&'a Document<'a>),
     /// A document broken into multiple lines.
     Break {
         broken: bool,
         document: &'a Document<'a>,
     },
+    /// A document with its cached broken flag.
+    ///
+    /// The flag tells if the document contains any broken documents so that
+    /// [`is_broken`](crate::utility::is_broken) does not traverse it again.
+    Cache {
+        broken: bool,
+        document: &'a Document<'a>,
+    },
+    /// A cursor.
+    ///
+    /// A formatter records its position in a formatted output.
+    Cursor,
+    /// A document dedented by a level.
+    Dedent(&'a Document<'a>),
+    /// A document dedented to a root.
+    ///
+    /// If it is `offside`, its indent becomes the one of the innermost
+    /// [`Offside`](Document::Offside) document. Otherwise, it is zero.
+    DedentToRoot {
+        document: &'a Document<'a>,
+        offside: bool,
+    },
+    /// A fill of documents.
+    ///
+    /// It alternates contents and separators. A formatter breaks each
+    /// separator only if its next content does not fit in a current line and
+    /// renders each content flat if it fits in the line.
+    Fill(&'a [Document<'a>]),
+    /// A group of documents.
+    ///
+    /// A formatter breaks it into multiple lines if it contains any broken
+    /// documents, or if its parent document is broken and it does not fit in
+    /// a maximum line width.
+    Group(&'a Document<'a>),
+    /// A hard line.
+    ///
+    /// A formatter always renders it as a newline even if a document is not
+    /// broken by [`Break`](Document::Break).
+    HardLine,
+    /// A document rendered differently depending on if its parent document is
+    /// broken or not.
+    IfBreak {
+        broken: &'a Document<'a>,
+        flat: &'a Document<'a>,
+    },
     /// An indented document.
     Indent(&'a Document<'a>),
     /// A line.
@@ -28,6 +85,14 @@ pub enum Document<'a> {
     Line,
     /// A line suffix.
     LineSuffix(&'a str),
+    /// A line suffix boundary.
+    ///
+    /// A formatter renders a newline if any line suffixes are pending.
+    LineSuffixBoundary,
+    /// A mark of a position.
+    ///
+    /// A formatter records its position in a formatted output with its ID.
+    Mark(usize),
     /// A document indented to a current column.
     ///
     /// If it is `soft`, an indent becomes equal to or more than a current
@@ -36,10 +101,43 @@ pub enum Document<'a> {
         document: &'a Document<'a>,
         soft: bool,
     },
+    /// A document with a prefix of lines.
+    ///
+    /// A formatter renders the prefix after an indent at the start of each
+    /// line following a newline in the document. The document is indented
+    /// relatively to the prefix. On empty lines, the prefix is rendered
+    /// without its trailing whitespace.
+    Prefix(&'a str, &'a Document<'a>),
     /// A sequence of documents.
     Sequence(&'a [Document<'a>]),
+    /// A soft line.
+    ///
+    /// A formatter considers it as nothing if a document is not broken by
+    /// [`Break`](Document::Break).
+    SoftLine,
+    /// A table of documents.
+    ///
+    /// A formatter renders its rows on separate lines and pads its cells with
+    /// spaces so that cells in each column are aligned by `alignments`.
+    /// Columns without alignments are aligned to the left. Cells are rendered
+    /// flat. If a cell is broken or does not fit in a maximum width, the
+    /// formatter renders the cell and the rest of its row without padding.
+    Table {
+        rows: &'a [&'a [Document<'a>]],
+        alignments: &'a [ColumnAlignment],
+    },
     /// A string.
+    ///
+    /// It must not contain any newline. Use [`Verbatim`](Document::Verbatim)
+    /// for multi-line strings.
     String(&'a str),
+    /// A verbatim string possibly with newlines.
+    ///
+    /// A formatter renders its newlines with a configured line ending but
+    /// without line suffixes. If it is `indent`ed, its continuation lines are
+    /// indented to a current indent. Otherwise, they start right after
+    /// current line prefixes.
+    Verbatim { string: &'a str, indent: bool },
 }
 
 impl<'a> From<&'a str> for Document<'a> {
@@ -1,109 +1,795 @@
+mod null_writer;
 mod state;
+mod task;
+mod writer;
 
-use crate::{FormatOptions, document::Document};
-use alloc::{string::ToString, vec, vec::Vec};
+use crate::{
+    ColumnAlignment, FormatOptions, FormattedRange, Mapping, Position, WidthUnit,
+    document::Document, utility::is_broken,
+};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use core::{
     fmt::{self, Write},
     iter::repeat_n,
 };
+use null_writer::NullWriter;
 use state::State;
+use task::Task;
+use unicode_width::UnicodeWidthStr;
+use writer::Writer;
 
 #[derive(Debug)]
 struct Context<'a, W: Write> {
-    writer: &'a mut W,
+    writer: Writer<'a, W>,
+    line: usize,
     column: usize,
+    content_column: usize,
     next_indent: usize,
+    next_alignment: usize,
+    next_prefix: Option<usize>,
+    prefixes: Vec<String>,
     line_suffixes: Vec<&'a str>,
+    line_suffix_column: Option<usize>,
+    pending_line_suffix_column: Option<usize>,
+    max_code_column: usize,
+    dry_run: bool,
     space: &'a str,
     indent: usize,
+    max_width: usize,
+    width_unit: WidthUnit,
+    tab_width: usize,
+    smart_tab: bool,
+    line_ending: &'static str,
+    mappings: Vec<Mapping>,
+    cursor: Option<Position>,
 }
 
 /// Formats a document.
-pub fn format(document: &Document, mut writer: impl Write, options: FormatOptions) -> fmt::Result {
+pub fn format(document: &Document, writer: impl Write, options: FormatOptions) -> fmt::Result {
+    format_with_positions(document, writer, options)?;
+
+    Ok(())
+}
+
+/// Formats a document with a source map.
+///
+/// It returns positions of [`Mark`](Document::Mark)s in a formatted output. A
+/// mark at the end of a line is positioned before its unrendered indent or
+/// trimmed whitespace.
+pub fn format_with_source_map(
+    document: &Document,
+    writer: impl Write,
+    options: FormatOptions,
+) -> Result<Vec<Mapping>, fmt::Error> {
+    Ok(format_with_positions(document, writer, options)?.0)
+}
+
+/// Formats a document with a cursor.
+///
+/// It returns a position of [`Cursor`](Document::Cursor) in a formatted output.
+/// If a document has multiple cursors, it returns the last one.
+pub fn format_with_cursor(
+    document: &Document,
+    writer: impl Write,
+    options: FormatOptions,
+) -> Result<Option<Position>, fmt::Error> {
+    Ok(format_with_positions(document, writer, options)?.1)
+}
+
+/// Formats lines covering a range between two marks in a document.
+///
+/// It returns the lines including their indents and a byte range of them in a
+/// whole output. It returns `None` if any of the marks is not found.
+pub fn format_range(
+    document: &Document,
+    start: usize,
+    end: usize,
+    options: FormatOptions,
+) -> Result<Option<FormattedRange>, fmt::Error> {
+    let mut string = String::new();
+    let mappings = format_with_source_map(document, &mut string, options)?;
+    let find = |id| {
+        mappings
+            .iter()
+            .find(|mapping| mapping.id() == id)
+            .map(|mapping| mapping.position().offset())
+    };
+    let (Some(start), Some(end)) = (find(start), find(end)) else {
+        return Ok(None);
+    };
+    let (start, end) = (start.min(end), start.max(end));
+    let line_endings = ['\n', '\r'];
+    let start = string[..start]
+        .rfind(line_endings)
+        .map_or(0, |index| index + 1);
+    let end = string[end..]
+        .find(line_endings)
+        .map_or(string.len(), |index| end + index);
+
+    Ok(Some(FormattedRange::new(
+        string[start..end].into(),
+        start..end,
+    )))
+}
+
+fn format_with_positions(
+    document: &Document,
+    mut writer: impl Write,
+    options: FormatOptions,
+) -> Result<(Vec<Mapping>, Option<Position>), fmt::Error> {
     let space = options.space().to_string();
     let mut context = Context {
-        writer: &mut writer,
+        writer: Writer::new(&mut writer, options.trim_trailing_whitespace()),
+        line: 0,
         column: 0,
+        content_column: 0,
         next_indent: 0,
+        next_alignment: 0,
+        next_prefix: None,
+        prefixes: vec![],
         line_suffixes: vec![],
+        line_suffix_column: None,
+        pending_line_suffix_column: None,
+        max_code_column: 0,
+        dry_run: false,
         space: &space,
         indent: options.indent(),
+        max_width: options.max_width(),
+        width_unit: options.width_unit(),
+        tab_width: options.tab_width().max(1),
+        smart_tab: options.smart_tab(),
+        line_ending: options.line_ending().as_str(),
+        mappings: vec![],
+        cursor: None,
     };
 
-    format_document(&mut context, document, Default::default())
+    format_document(&mut context, document, Default::default(), &[])?;
+    write_prefix(&mut context)?;
+    write_line_suffixes(&mut context)?;
+
+    trim_positions(&mut context);
+
+    Ok((context.mappings, context.cursor))
 }
 
+// Formats a document. Outer tasks are the ones pending after the document and
+// only used to check if groups fit.
 fn format_document<'a>(
     context: &mut Context<'a, impl Write>,
-    document: &'a Document,
+    document: &'a Document<'a>,
     state: State,
+    outer_tasks: &[(Task<'a>, State)],
 ) -> fmt::Result {
-    match document {
-        Document::Break { broken, document } => {
-            format_document(context, document, state.set_broken(*broken))?
-        }
-        Document::Indent(document) => {
-            format_document(
-                context,
-                document,
+    let mut tasks = vec![(Task::Document(document), state)];
+
+    while let Some((task, state)) = tasks.pop() {
+        let document = match task {
+            Task::Document(document) => document,
+            Task::Fill([content, rest @ ..]) => {
+                let mut column = context.column;
+                let broken =
+                    state.broken() && !fits(context, content, false, &mut column).unwrap_or(true);
+
+                tasks.push((Task::FillSeparator(rest), state));
+                tasks.push((Task::Document(content), state.set_broken(broken)));
+                continue;
+            }
+            Task::FillSeparator([separator, rest @ ..]) => {
+                let mut column = context.column;
+                let broken = state.broken()
+                    && !fits(context, separator, false, &mut column)
+                        .or_else(|| {
+                            rest.first()
+                                .and_then(|content| fits(context, content, false, &mut column))
+                        })
+                        .unwrap_or(true);
+
+                tasks.push((Task::Fill(rest), state));
+                tasks.push((Task::Document(separator), state.set_broken(broken)));
+                continue;
+            }
+            Task::Fill([]) | Task::FillSeparator([]) => continue,
+            Task::LineSuffixColumn(column) => {
+                context.line_suffix_column = column;
+                continue;
+            }
+            Task::Padding(width) => {
+                if width > 0 {
+                    flush(context)?;
+                }
+
+                for _ in 0..width {
+                    write_str(context, " ")?;
+                }
+
+                continue;
+            }
+        };
+
+        match document {
+            Document::Align(alignment, document) => tasks.push((
+                Task::Document(document),
+                state.set_alignment(state.alignment() + alignment),
+            )),
+            Document::AlignedLineSuffixes(document) => {
+                if !context.dry_run {
+                    tasks.push((Task::LineSuffixColumn(context.line_suffix_column), state));
+                    context.line_suffix_column =
+                        Some(measure_code(context, document, state, &tasks)?);
+                }
+
+                tasks.push((Task::Document(document), state));
+            }
+            Document::Break { broken, document } => {
+                tasks.push((Task::Document(document), state.set_broken(*broken)))
+            }
+            Document::Cache { document, .. } => tasks.push((Task::Document(document), state)),
+            Document::Group(document) => {
+                let broken = is_broken(document)
+                    || state.broken() && !fits_line(context, document, &tasks, outer_tasks);
+
+                tasks.push((Task::Document(document), state.set_broken(broken)))
+            }
+            Document::Dedent(document) => tasks.push((
+                Task::Document(document),
+                state.set_indent(state.indent().saturating_sub(context.indent)),
+            )),
+            Document::DedentToRoot { document, offside } => tasks.push((
+                Task::Document(document),
+                if *offside {
+                    state
+                        .set_indent(state.root_indent())
+                        .set_alignment(state.root_alignment())
+                } else {
+                    state.set_indent(0).set_alignment(0)
+                },
+            )),
+            Document::HardLine => write_line(context, state)?,
+            Document::IfBreak { broken, flat } => tasks.push((
+                Task::Document(if state.broken() { broken } else { flat }),
+                state,
+            )),
+            Document::Indent(document) => tasks.push((
+                Task::Document(document),
                 state.set_indent(state.indent() + context.indent),
-            )?;
-        }
-        Document::Line => {
-            if state.broken() {
-                for string in context.line_suffixes.drain(..).chain(["\n"]) {
-                    context.writer.write_str(string)?;
+            )),
+            Document::Line => {
+                if state.broken() {
+                    write_line(context, state)?;
+                } else {
+                    write_str(context, " ")?;
+                }
+            }
+            Document::LineSuffix(suffix) => {
+                if !suffix.is_empty() {
+                    flush(context)?;
+
+                    if context.line_suffix_column.is_some() {
+                        context.pending_line_suffix_column = context.line_suffix_column;
+                    }
                 }
 
-                context.next_indent = state.indent();
-                context.column = state.indent();
-            } else {
-                context.writer.write_char(' ')?;
-                context.column += 1;
+                context.line_suffixes.push(suffix);
             }
-        }
-        Document::LineSuffix(suffix) => {
-            if !suffix.is_empty() {
-                flush(context)?;
+            Document::LineSuffixBoundary => {
+                if has_line_suffixes(context) {
+                    write_line(context, state)?;
+                }
+            }
+            Document::Cursor => context.cursor = Some(position(context)),
+            Document::Mark(id) => {
+                let position = position(context);
+
+                context.mappings.push(Mapping::new(*id, position));
+            }
+            Document::Offside { document, soft } => {
+                let space_width = advance(context, 0, context.space).max(1);
+                let indent = state.indent() * space_width;
+                let column = context.column.saturating_sub(prefix_width(context, state));
+                let column = if *soft {
+                    column.max(indent + state.alignment())
+                } else {
+                    column
+                };
+
+                let state = if context.smart_tab && column >= indent {
+                    state.set_alignment(column - indent)
+                } else {
+                    state
+                        .set_indent(column / space_width)
+                        .set_alignment(column % space_width)
+                };
+
+                tasks.push((
+                    Task::Document(document),
+                    state
+                        .set_root_indent(state.indent())
+                        .set_root_alignment(state.alignment()),
+                ))
+            }
+            Document::Fill(documents) => tasks.push((Task::Fill(documents), state)),
+            Document::Prefix(prefix, document) => {
+                let mut string = state
+                    .prefix()
+                    .map(|index| context.prefixes[index].clone())
+                    .unwrap_or_default();
+
+                string.extend(
+                    repeat_n(context.space, state.indent()).chain(repeat_n(" ", state.alignment())),
+                );
+                string.push_str(prefix);
+                context.prefixes.push(string);
+
+                tasks.push((
+                    Task::Document(document),
+                    State::default()
+                        .set_broken(state.broken())
+                        .set_prefix(Some(context.prefixes.len() - 1)),
+                ))
+            }
+            Document::Sequence(sequence) => tasks.extend(
+                sequence
+                    .iter()
+                    .rev()
+                    .map(|document| (Task::Document(document), state)),
+            ),
+            Document::SoftLine => {
+                if state.broken() {
+                    write_line(context, state)?;
+                }
+            }
+            Document::String(string) => {
+                if !string.is_empty() {
+                    flush(context)?;
+                }
+
+                write_str(context, string)?;
+            }
+            Document::Table { rows, alignments } => {
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| (cell, measure_cell(context, cell)))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let mut widths = vec![];
+
+                for row in &rows {
+                    for (index, (_, width)) in row.iter().enumerate() {
+                        let Some(width) = width else {
+                            break;
+                        };
+
+                        if index >= widths.len() {
+                            widths.push(0);
+                        }
+
+                        widths[index] = widths[index].max(*width);
+                    }
+                }
+
+                let mut table = vec![];
+
+                for (index, row) in rows.iter().enumerate() {
+                    if index > 0 {
+                        table.push((Task::Document(&Document::HardLine), state));
+                    }
+
+                    let mut aligned = true;
+
+                    for (index, &(cell, width)) in row.iter().enumerate() {
+                        aligned &= width.is_some();
+
+                        let Some(width) = width.filter(|_| aligned) else {
+                            table.push((Task::Document(cell), state));
+                            continue;
+                        };
+                        let padding = Task::Padding(widths[index] - width);
+                        let cell = (Task::Document(cell), state.set_broken(false));
+
+                        match alignments.get(index).copied().unwrap_or_default() {
+                            ColumnAlignment::Left => {
+                                table.push(cell);
+
+                                if index + 1 < row.len() {
+                                    table.push((padding, state));
+                                }
+                            }
+                            ColumnAlignment::Right => table.extend([(padding, state), cell]),
+                        }
+                    }
+                }
+
+                tasks.extend(table.into_iter().rev());
             }
+            Document::Verbatim { string, indent } => {
+                for (index, line) in lines(string).enumerate() {
+                    if index > 0 {
+                        write_newline(
+                            context,
+                            if *indent {
+                                state
+                            } else {
+                                State::default().set_prefix(state.prefix())
+                            },
+                        )?;
+                    }
+
+                    if !line.is_empty() {
+                        flush(context)?;
+                    }
 
-            context.line_suffixes.push(suffix);
+                    write_str(context, line)?;
+                }
+            }
         }
-        Document::Offside { document, soft } => format_document(
-            context,
-            document,
-            state.set_indent(if *soft {
-                context.column.max(state.indent())
-            } else {
-                context.column
-            }),
-        )?,
-        Document::Sequence(documents) => {
-            for document in *documents {
-                format_document(context, document, state)?;
+    }
+
+    Ok(())
+}
+
+// Checks if a document fits in a maximum width until its first newline.
+//
+// It returns `None` if it reaches the end of the document without any newline.
+fn fits(
+    context: &Context<impl Write>,
+    document: &Document,
+    broken: bool,
+    column: &mut usize,
+) -> Option<bool> {
+    let mut documents = vec![(document, broken)];
+
+    while let Some((document, broken)) = documents.pop() {
+        let fit = match document {
+            Document::Break { broken, document } => {
+                documents.push((document, *broken));
+                None
+            }
+            Document::Cache { document, .. } => {
+                documents.push((document, broken));
+                None
+            }
+            Document::Group(document) => {
+                documents.push((document, false));
+                None
+            }
+            Document::HardLine => Some(true),
+            Document::IfBreak {
+                broken: broken_document,
+                flat,
+            } => {
+                documents.push((if broken { broken_document } else { flat }, broken));
+                None
+            }
+            Document::Align(_, document)
+            | Document::AlignedLineSuffixes(document)
+            | Document::Dedent(document)
+            | Document::DedentToRoot { document, .. }
+            | Document::Indent(document)
+            | Document::Offside { document, .. }
+            | Document::Prefix(_, document) => {
+                documents.push((document, broken));
+                None
+            }
+            Document::Line => {
+                if broken {
+                    Some(true)
+                } else {
+                    consume(context, column, " ")
+                }
+            }
+            Document::Cursor | Document::LineSuffix(_) | Document::Mark(_) => None,
+            Document::LineSuffixBoundary => has_line_suffixes(context).then_some(true),
+            Document::Fill(sequence) | Document::Sequence(sequence) => {
+                documents.extend(sequence.iter().rev().map(|document| (document, broken)));
+                None
+            }
+            Document::SoftLine => broken.then_some(true),
+            Document::String(string) => consume(context, column, string),
+            Document::Table { rows, .. } => {
+                if rows.len() > 1 {
+                    documents.push((&Document::HardLine, broken));
+                }
+
+                documents.extend(
+                    rows.iter()
+                        .take(1)
+                        .flat_map(|row| row.iter().rev())
+                        .map(|document| (document, false)),
+                );
+                None
+            }
+            Document::Verbatim { string, .. } => {
+                let mut lines = lines(string);
+
+                consume(context, column, lines.next().unwrap_or_default())
+                    .or_else(|| lines.next().map(|_| true))
             }
+        };
+
+        if fit.is_some() {
+            return fit;
         }
-        Document::String(string) => {
-            if !string.is_empty() {
-                flush(context)?;
+    }
+
+    None
+}
+
+// Measures a width of a cell rendered flat.
+//
+// It returns `None` if the cell is broken or does not fit in a maximum width.
+fn measure_cell(context: &Context<impl Write>, cell: &Document) -> Option<usize> {
+    let mut column = 0;
+
+    (!is_broken(cell) && fits(context, cell, false, &mut column).is_none()).then_some(column)
+}
+
+// Checks if a flat document followed by pending tasks fits in a maximum width
+// until its first newline.
+fn fits_line(
+    context: &Context<impl Write>,
+    document: &Document,
+    tasks: &[(Task, State)],
+    outer_tasks: &[(Task, State)],
+) -> bool {
+    // Nothing overflows an unlimited width.
+    if context.max_width == usize::MAX {
+        return true;
+    }
+
+    let mut column = context.column;
+
+    if let Some(fit) = fits(context, document, false, &mut column) {
+        return fit;
+    }
+
+    for (task, state) in tasks.iter().rev().chain(outer_tasks.iter().rev()) {
+        let fit = match task {
+            Task::Document(document) => fits(context, document, state.broken(), &mut column),
+            Task::Fill(documents) | Task::FillSeparator(documents) => documents
+                .iter()
+                .find_map(|document| fits(context, document, state.broken(), &mut column)),
+            Task::LineSuffixColumn(_) => None,
+            Task::Padding(width) => {
+                column += width;
+                (column > context.max_width).then_some(false)
             }
+        };
+
+        if let Some(fit) = fit {
+            return fit;
+        }
+    }
+
+    true
+}
+
+fn consume(context: &Context<impl Write>, column: &mut usize, string: &str) -> Option<bool> {
+    *column = advance(context, *column, string);
+
+    (*column > context.max_width).then_some(false)
+}
+
+// Computes a column after a string with tab stops.
+fn advance(context: &Context<impl Write>, column: usize, string: &str) -> usize {
+    let mut strings = string.split('\t');
+    let mut column = column
+        + strings
+            .next()
+            .map_or(0, |string| measure(string, context.width_unit));
+
+    for string in strings {
+        column = (column / context.tab_width + 1) * context.tab_width
+            + measure(string, context.width_unit);
+    }
+
+    column
+}
+
+fn measure(string: &str, unit: WidthUnit) -> usize {
+    match unit {
+        WidthUnit::Byte => string.len(),
+        WidthUnit::Char => string.chars().count(),
+        WidthUnit::Display => string.width(),
+    }
+}
+
+fn has_line_suffixes(context: &Context<impl Write>) -> bool {
+    context
+        .line_suffixes
+        .iter()
+        .any(|suffix| !suffix.is_empty())
+}
 
-            context.writer.write_str(string)?;
-            context.column += string.len();
+fn write_line(context: &mut Context<impl Write>, state: State) -> fmt::Result {
+    write_line_suffixes(context)?;
+    write_newline(context, state)
+}
+
+fn write_line_suffixes(context: &mut Context<impl Write>) -> fmt::Result {
+    if has_line_suffixes(context) && context.content_column > 0 {
+        context.max_code_column = context.max_code_column.max(context.content_column);
+
+        if let Some(column) = context.pending_line_suffix_column {
+            // Trimmed whitespace is not included in the content column.
+            context.writer.clear_whitespace();
+
+            for _ in context.content_column..column {
+                context.writer.write_str(" ")?;
+            }
         }
     }
 
+    context.pending_line_suffix_column = None;
+
+    for string in context.line_suffixes.drain(..) {
+        context.writer.write_str(string)?;
+    }
+
+    Ok(())
+}
+
+// Measures a maximum column of code followed by line suffixes in a document
+// by formatting it without any output.
+fn measure_code<'a>(
+    context: &Context<'a, impl Write>,
+    document: &'a Document<'a>,
+    state: State,
+    tasks: &[(Task<'a>, State)],
+) -> Result<usize, fmt::Error> {
+    let mut writer = NullWriter;
+    let mut dry_context = Context {
+        writer: Writer::new(&mut writer, context.writer.trim()),
+        line: context.line,
+        column: context.column,
+        content_column: context.content_column,
+        next_indent: context.next_indent,
+        next_alignment: context.next_alignment,
+        next_prefix: context.next_prefix,
+        prefixes: context.prefixes.clone(),
+        line_suffixes: context.line_suffixes.clone(),
+        line_suffix_column: None,
+        pending_line_suffix_column: None,
+        max_code_column: 0,
+        dry_run: true,
+        space: context.space,
+        indent: context.indent,
+        max_width: context.max_width,
+        width_unit: context.width_unit,
+        tab_width: context.tab_width,
+        smart_tab: context.smart_tab,
+        line_ending: context.line_ending,
+        mappings: vec![],
+        cursor: None,
+    };
+
+    format_document(&mut dry_context, document, state, tasks)?;
+    // Include line suffixes written after the document.
+    write_line_suffixes(&mut dry_context)?;
+
+    Ok(dry_context.max_code_column)
+}
+
+// Writes a newline without line suffixes.
+fn write_newline(context: &mut Context<impl Write>, state: State) -> fmt::Result {
+    write_prefix(context)?;
+    trim_positions(context);
+    context.writer.write_str(context.line_ending)?;
+    context.line += 1;
+    context.content_column = 0;
+
+    context.next_indent = state.indent();
+    context.next_alignment = state.alignment();
+    context.next_prefix = state.prefix();
+    context.column = (0..state.indent()).fold(prefix_width(context, state), |column, _| {
+        advance(context, column, context.space)
+    }) + state.alignment();
+
+    Ok(())
+}
+
+// Writes a pending prefix without trailing whitespace on an empty line.
+fn write_prefix(context: &mut Context<impl Write>) -> fmt::Result {
+    if let Some(index) = context.next_prefix.take() {
+        let prefix = context.prefixes[index].trim_end();
+
+        context.content_column = advance(context, 0, prefix);
+        context.writer.write_str(prefix)?;
+    }
+
+    Ok(())
+}
+
+fn prefix_width(context: &Context<impl Write>, state: State) -> usize {
+    state
+        .prefix()
+        .map_or(0, |index| advance(context, 0, &context.prefixes[index]))
+}
+
+// Splits a string into lines without line endings.
+fn lines(string: &str) -> impl Iterator<Item = &str> {
+    string
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+fn write_str(context: &mut Context<impl Write>, string: &str) -> fmt::Result {
+    context.writer.write_str(string)?;
+
+    let content = context.writer.trim_end(string);
+
+    if !content.is_empty() {
+        context.content_column = advance(context, context.column, content);
+    }
+
+    context.column = advance(context, context.column, string);
+
     Ok(())
 }
 
+// Returns a current position including an unrendered indent.
+fn position(context: &Context<impl Write>) -> Position {
+    Position::new(
+        context.writer.offset()
+            + context
+                .next_prefix
+                .map_or(0, |index| context.prefixes[index].len())
+            + context.next_indent * context.space.len()
+            + context.next_alignment,
+        context.line,
+        context.column,
+    )
+}
+
+// Moves positions in trailing whitespace of a current line to its end.
+fn trim_positions(context: &mut Context<impl Write>) {
+    let position = Position::new(
+        context.writer.trimmed_offset(),
+        context.line,
+        context.content_column,
+    );
+    let trim = |original: Position| {
+        if original.offset() > position.offset() {
+            position
+        } else {
+            original
+        }
+    };
+
+    for mapping in context
+        .mappings
+        .iter_mut()
+        .rev()
+        .take_while(|mapping| mapping.position().line() == context.line)
+    {
+        *mapping = Mapping::new(mapping.id(), trim(mapping.position()));
+    }
+
+    context.cursor = context.cursor.map(trim);
+}
+
 fn flush(context: &mut Context<impl Write>) -> fmt::Result {
-    // Flush an indent lazily.
-    for string in repeat_n(context.space, context.next_indent) {
+    // Flush a prefix, indent, and alignment lazily.
+    if let Some(index) = context.next_prefix {
+        context.writer.write_str(&context.prefixes[index])?;
+    }
+
+    for string in
+        repeat_n(context.space, context.next_indent).chain(repeat_n(" ", context.next_alignment))
+    {
         context.writer.write_str(string)?;
     }
 
     // Do not render any indent until the next newline.
     context.next_indent = 0;
+    context.next_alignment = 0;
+    context.next_prefix = None;
 
     Ok(())
 }
@@ -112,6 +798,7 @@ fn flush(context: &mut Context<impl Write>) -> fmt::Result {
 mod tests {
     use super::{super::build::*, *};
     use alloc::{boxed::Box, string::String};
+    use bumpalo::Bump;
     use indoc::indoc;
     use pretty_assertions::assert_eq;
 
@@ -207,68 +894,110 @@ mod tests {
         }
     }
 
-    mod line_suffix {
+    mod hard_line {
         use super::*;
         use pretty_assertions::assert_eq;
 
         #[test]
-        fn format_line_suffix_between_strings() {
+        fn format_in_flat_document() {
             assert_eq!(
                 format_to_string(
-                    &sequence(&["{".into(), line_suffix("foo"), "}".into(), line()]),
+                    &flatten(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        "// bar".into(),
+                        hard_line(),
+                        "baz".into(),
+                    ])),
                     default_options()
                 ),
-                "{}foo\n",
+                "foo // bar\nbaz"
             );
         }
 
         #[test]
-        fn format_two_line_suffixes_between_strings() {
+        fn format_with_indent() {
             assert_eq!(
                 format_to_string(
-                    &sequence(&[
-                        "{".into(),
-                        line_suffix("foo"),
-                        line_suffix("bar"),
-                        "}".into(),
-                        line()
-                    ]),
+                    &flatten(&indent(&sequence(&[
+                        "foo".into(),
+                        hard_line(),
+                        "bar".into(),
+                    ]))),
                     default_options()
                 ),
-                "{}foobar\n",
+                "foo\n  bar"
+            );
+        }
+
+        #[test]
+        fn format_in_flatten_if() {
+            assert_eq!(
+                format_to_string(
+                    &flatten_if(
+                        true,
+                        &sequence(&["foo".into(), line(), "bar".into(), hard_line()])
+                    ),
+                    default_options()
+                ),
+                "foo\nbar\n"
+            );
+        }
+
+        #[test]
+        fn format_in_group() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        "bar".into(),
+                        hard_line()
+                    ])),
+                    default_options()
+                ),
+                "foo\nbar\n"
             );
         }
     }
 
-    mod offside {
+    mod if_break {
         use super::*;
         use pretty_assertions::assert_eq;
 
-        fn create_group() -> Document<'static> {
+        fn create_arguments() -> Document<'static> {
             sequence(allocate([
-                "foo".into(),
+                "f(".into(),
                 indent(allocate(sequence(allocate([
                     line(),
-                    offside(
-                        allocate(r#break(allocate(sequence(allocate([
-                            "bar".into(),
-                            line(),
-                            "baz".into(),
-                        ]))))),
-                        false,
-                    ),
+                    "foo,".into(),
+                    line(),
+                    "bar".into(),
+                    if_break(allocate(",".into()), allocate("".into())),
                 ])))),
+                line(),
+                ")".into(),
             ]))
         }
 
         #[test]
-        fn format_flat_group() {
+        fn format_flat() {
             assert_eq!(
-                format_to_string(&flatten(&create_group()), default_options().set_indent(2)),
+                format_to_string(&flatten(&create_arguments()), default_options()),
+                "f( foo, bar )"
+            );
+        }
+
+        #[test]
+        fn format_broken() {
+            assert_eq!(
+                format_to_string(&r#break(&create_arguments()), default_options()),
                 indoc!(
                     "
-                    foo bar
-                        baz
+                    f(
+                      foo,
+                      bar,
+                    )
                     "
                 )
                 .trim(),
@@ -276,253 +1005,2459 @@ mod tests {
         }
 
         #[test]
-        fn format_broken_group() {
+        fn format_in_group() {
             assert_eq!(
-                format_to_string(&r#break(&create_group()), default_options().set_indent(2)),
+                format_to_string(
+                    &group(&create_arguments()),
+                    default_options().set_max_width(12)
+                ),
                 indoc!(
                     "
-                    foo
-                      bar
-                      baz
+                    f(
+                      foo,
+                      bar,
+                    )
                     "
                 )
                 .trim(),
             );
+            assert_eq!(
+                format_to_string(
+                    &group(&create_arguments()),
+                    default_options().set_max_width(13)
+                ),
+                "f( foo, bar )"
+            );
         }
+    }
 
-        mod nested {
-            use super::*;
-            use pretty_assertions::assert_eq;
+    mod soft_line {
+        use super::*;
+        use pretty_assertions::assert_eq;
 
-            fn create_groups(
-                inner: for<'a> fn(&'a Document<'a>) -> Document<'a>,
-            ) -> Document<'static> {
-                sequence(allocate([
-                    "foo".into(),
-                    indent(allocate(sequence(allocate([
-                        line(),
-                        offside(
-                            allocate(r#break(allocate(sequence(allocate([
-                                "bar".into(),
-                                line(),
-                                "baz".into(),
-                                line(),
-                                inner(allocate(sequence(allocate([
-                                    "qux".into(),
-                                    indent(allocate(sequence(allocate([
-                                        line(),
-                                        offside(
-                                            allocate(r#break(allocate(sequence(allocate([
-                                                "quux".into(),
-                                                line(),
-                                                "corge".into(),
-                                            ]))))),
-                                            false,
-                                        ),
-                                    ])))),
-                                ])))),
-                            ]))))),
-                            false,
-                        ),
-                    ])))),
-                ]))
-            }
+        fn create_arguments() -> Document<'static> {
+            sequence(allocate([
+                "f(".into(),
+                indent(allocate(sequence(allocate([
+                    soft_line(),
+                    "foo,".into(),
+                    line(),
+                    "bar".into(),
+                ])))),
+                soft_line(),
+                ")".into(),
+            ]))
+        }
+
+        #[test]
+        fn format_flat() {
+            assert_eq!(
+                format_to_string(&flatten(&create_arguments()), default_options()),
+                "f(foo, bar)"
+            );
+        }
+
+        #[test]
+        fn format_broken() {
+            assert_eq!(
+                format_to_string(&r#break(&create_arguments()), default_options()),
+                indoc!(
+                    "
+                    f(
+                      foo,
+                      bar
+                    )
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_in_group() {
+            assert_eq!(
+                format_to_string(
+                    &group(&create_arguments()),
+                    default_options().set_max_width(11)
+                ),
+                "f(foo, bar)"
+            );
+            assert_eq!(
+                format_to_string(
+                    &group(&create_arguments()),
+                    default_options().set_max_width(10)
+                ),
+                indoc!(
+                    "
+                    f(
+                      foo,
+                      bar
+                    )
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_line_suffix() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "foo".into(),
+                        line_suffix(" // bar"),
+                        soft_line(),
+                        "baz".into()
+                    ]),
+                    default_options()
+                ),
+                "foo // bar\nbaz"
+            );
+        }
+    }
+
+    mod line_suffix {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn format_line_suffix_between_strings() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&["{".into(), line_suffix("foo"), "}".into(), line()]),
+                    default_options()
+                ),
+                "{}foo\n",
+            );
+        }
+
+        #[test]
+        fn format_two_line_suffixes_between_strings() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "{".into(),
+                        line_suffix("foo"),
+                        line_suffix("bar"),
+                        "}".into(),
+                        line()
+                    ]),
+                    default_options()
+                ),
+                "{}foobar\n",
+            );
+        }
+
+        #[test]
+        fn format_line_suffix_at_end() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&["foo".into(), line_suffix(" // bar")]),
+                    default_options()
+                ),
+                "foo // bar",
+            );
+        }
+
+        #[test]
+        fn format_line_suffix_boundary() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&sequence(&[
+                        "foo".into(),
+                        line_suffix(" // bar"),
+                        line_suffix_boundary(),
+                        "baz".into(),
+                    ])),
+                    default_options()
+                ),
+                "foo // bar\n  baz",
+            );
+        }
+
+        #[test]
+        fn format_line_suffix_boundary_without_line_suffix() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "foo".into(),
+                        line_suffix(""),
+                        line_suffix_boundary(),
+                        "bar".into(),
+                    ]),
+                    default_options()
+                ),
+                "foobar",
+            );
+        }
+
+        #[test]
+        fn format_line_suffix_boundary_in_group() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "foo".into(),
+                        line_suffix(" // bar"),
+                        group(&sequence(&[
+                            "(".into(),
+                            line_suffix_boundary(),
+                            soft_line(),
+                            ")".into()
+                        ])),
+                    ]),
+                    default_options()
+                ),
+                "foo( // bar\n)",
+            );
+        }
+
+        mod aligned {
+            use super::*;
+            use pretty_assertions::assert_eq;
 
             #[test]
-            fn format_flat_outer_with_flat_inner() {
+            fn format_aligned_line_suffixes() {
                 assert_eq!(
                     format_to_string(
-                        &flatten(&create_groups(flatten)),
-                        default_options().set_indent(2)
+                        &aligned_line_suffixes(&sequence(&[
+                            "a".into(),
+                            line_suffix(" // a"),
+                            hard_line(),
+                            "foo".into(),
+                            line_suffix(" // foo"),
+                            hard_line(),
+                            "bar".into(),
+                        ])),
+                        default_options()
                     ),
-                    indoc!(
-                        "
-                    foo bar
-                        baz
-                        qux quux
-                            corge
-                    "
-                    )
-                    .trim(),
+                    "a   // a\nfoo // foo\nbar"
                 );
             }
 
             #[test]
-            fn format_flat_outer_with_broken_inner() {
+            fn format_line_without_line_suffix() {
                 assert_eq!(
                     format_to_string(
-                        &flatten(&create_groups(r#break)),
-                        default_options().set_indent(2)
+                        &aligned_line_suffixes(&sequence(&[
+                            "a".into(),
+                            line_suffix(" // a"),
+                            hard_line(),
+                            "foobar".into(),
+                            hard_line(),
+                            "bc".into(),
+                            line_suffix(" // bc"),
+                        ])),
+                        default_options()
                     ),
-                    indoc!(
-                        "
-                    foo bar
-                        baz
-                        qux
-                          quux
-                          corge
-                    "
-                    )
-                    .trim(),
+                    "a  // a\nfoobar\nbc // bc"
                 );
             }
 
             #[test]
-            fn format_broken_outer_with_flat_inner() {
+            fn format_line_suffix_without_code() {
                 assert_eq!(
                     format_to_string(
-                        &r#break(&create_groups(flatten)),
-                        default_options().set_indent(2)
+                        &aligned_line_suffixes(&sequence(&[
+                            "foo".into(),
+                            line_suffix(" // foo"),
+                            hard_line(),
+                            line_suffix("// bar"),
+                            hard_line(),
+                            "a".into(),
+                            line_suffix(" // a"),
+                        ])),
+                        default_options()
                     ),
-                    indoc!(
-                        "
-                    foo
-                      bar
-                      baz
-                      qux quux
-                          corge
-                    "
-                    )
-                    .trim(),
+                    "foo // foo\n// bar\na   // a"
                 );
             }
 
             #[test]
-            fn format_broken_outer_with_broken_inner() {
+            fn format_indented_line_suffixes() {
                 assert_eq!(
                     format_to_string(
-                        &r#break(&create_groups(r#break)),
-                        default_options().set_indent(2)
+                        &sequence(&[
+                            "{".into(),
+                            indent(&aligned_line_suffixes(&sequence(&[
+                                hard_line(),
+                                "a".into(),
+                                line_suffix(" // a"),
+                                hard_line(),
+                                "foo".into(),
+                                line_suffix(" // foo"),
+                            ]))),
+                            hard_line(),
+                            "}".into(),
+                        ]),
+                        default_options()
                     ),
                     indoc!(
                         "
-                    foo
-                      bar
-                      baz
-                      qux
-                        quux
-                        corge
-                    "
+                        {
+                          a   // a
+                          foo // foo
+                        }
+                        "
                     )
-                    .trim(),
+                    .trim()
                 );
             }
 
             #[test]
-            fn format_two_flat_groups() {
+            fn format_line_suffix_after_document() {
                 assert_eq!(
                     format_to_string(
-                        &flatten(&sequence(&[
-                            "qux".into(),
-                            line(),
-                            offside(
-                                &r#break(&sequence(&[
-                                    flatten(&create_group()),
-                                    line(),
-                                    flatten(&create_group())
-                                ])),
-                                false
-                            )
-                        ])),
-                        default_options().set_indent(1)
+                        &sequence(&[
+                            aligned_line_suffixes(&sequence(&[
+                                "a".into(),
+                                line_suffix(" // a"),
+                                hard_line(),
+                                "foo".into(),
+                                line_suffix(" // foo"),
+                            ])),
+                            hard_line(),
+                            "bar".into(),
+                            line_suffix(" // bar"),
+                        ]),
+                        default_options()
                     ),
-                    indoc!(
-                        "
-                        qux foo bar
-                                baz
-                            foo bar
-                                baz
-                        "
-                    )
-                    .trim(),
+                    "a   // a\nfoo // foo\nbar // bar"
                 );
             }
-        }
 
-        mod soft {
-            use super::*;
-            use pretty_assertions::assert_eq;
+            #[test]
+            fn format_line_suffixes_in_group() {
+                let document = aligned_line_suffixes(allocate(sequence(allocate([
+                    group(allocate(sequence(allocate([
+                        "foo".into(),
+                        line(),
+                        "bar".into(),
+                    ])))),
+                    line_suffix(" // foo"),
+                    hard_line(),
+                    "a".into(),
+                    line_suffix(" // a"),
+                ]))));
+
+                assert_eq!(
+                    format_to_string(&document, default_options()),
+                    "foo bar // foo\na       // a"
+                );
+                assert_eq!(
+                    format_to_string(&document, default_options().set_max_width(6)),
+                    "foo\nbar // foo\na   // a"
+                );
+            }
 
             #[test]
-            fn format_less_indent() {
+            fn format_group_with_trailing_content_in_aligned_line_suffixes() {
                 assert_eq!(
                     format_to_string(
-                        &flatten(&sequence(&[
-                            "a".into(),
-                            indent(&sequence(&[
+                        &sequence(&[
+                            aligned_line_suffixes(&sequence(&[
+                                "a".into(),
+                                line_suffix(" // 1"),
                                 line(),
-                                offside(
-                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
-                                    true,
-                                ),
+                                group(&sequence(&["xx".into(), line(), "yyyy".into()])),
+                                line_suffix(" // 2"),
                             ])),
-                        ])),
-                        default_options().set_indent(1)
+                            ";;;;".into(),
+                        ]),
+                        default_options().set_max_width(10)
                     ),
-                    indoc!(
-                        "
-                    a b
-                      c
-                    "
-                    )
-                    .trim(),
+                    "a    // 1\nxx\nyyyy;;;; // 2"
                 );
             }
 
             #[test]
-            fn format_equal_indent() {
+            fn format_aligned_line_suffixes_with_trailing_whitespace() {
+                let document = aligned_line_suffixes(allocate(sequence(allocate([
+                    "a".into(),
+                    line_suffix(" // 1"),
+                    line(),
+                    "bbbb ".into(),
+                    line_suffix(" // 2"),
+                ]))));
+
+                assert_eq!(
+                    format_to_string(&document, default_options()),
+                    "a     // 1\nbbbb  // 2"
+                );
                 assert_eq!(
                     format_to_string(
-                        &flatten(&sequence(&[
-                            "a".into(),
-                            indent(&sequence(&[
-                                line(),
-                                offside(
-                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
-                                    true,
-                                ),
-                            ])),
-                        ])),
-                        default_options().set_indent(2)
+                        &document,
+                        default_options().set_trim_trailing_whitespace(true)
                     ),
-                    indoc!(
-                        "
-                    a b
-                      c
-                    "
-                    )
-                    .trim(),
+                    "a    // 1\nbbbb // 2"
                 );
             }
 
             #[test]
-            fn format_more_indent() {
+            fn format_nested_aligned_line_suffixes() {
                 assert_eq!(
                     format_to_string(
-                        &flatten(&sequence(&[
-                            "a".into(),
-                            indent(&sequence(&[
-                                line(),
-                                offside(
-                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
-                                    true,
-                                ),
+                        &aligned_line_suffixes(&sequence(&[
+                            "foo".into(),
+                            line_suffix(" // foo"),
+                            hard_line(),
+                            aligned_line_suffixes(&sequence(&[
+                                "a".into(),
+                                line_suffix(" // a"),
+                                hard_line(),
+                                "bc".into(),
+                                line_suffix(" // bc"),
                             ])),
                         ])),
-                        default_options().set_indent(3)
+                        default_options()
                     ),
-                    indoc!(
-                        "
-                    a b
-                       c
-                    "
-                    )
-                    .trim(),
+                    "foo // foo\na  // a\nbc // bc"
+                );
+            }
+
+            #[test]
+            fn format_deeply_nested_aligned_line_suffixes() {
+                let allocator = Bump::new();
+                let builder = Builder::new(&allocator);
+                let mut document = builder.sequence(["foo".into(), line_suffix(" // foo")]);
+
+                for _ in 0..1000 {
+                    document = builder.aligned_line_suffixes(builder.sequence([
+                        document,
+                        hard_line(),
+                        "a".into(),
+                        line_suffix(" // a"),
+                    ]));
+                }
+
+                assert!(
+                    format_to_string(&document, default_options())
+                        .starts_with("foo // foo\na   // a\n")
                 );
             }
         }
     }
 
+    mod align {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_lines() -> Document<'static> {
+            sequence(allocate(["foo".into(), hard_line(), "bar".into()]))
+        }
+
+        #[test]
+        fn format_aligned_document() {
+            assert_eq!(
+                format_to_string(&align(3, allocate(create_lines())), default_options()),
+                "foo\n   bar"
+            );
+        }
+
+        #[test]
+        fn format_nested_aligned_document() {
+            assert_eq!(
+                format_to_string(
+                    &align(1, &align(2, allocate(create_lines()))),
+                    default_options()
+                ),
+                "foo\n   bar"
+            );
+        }
+
+        #[test]
+        fn format_aligned_document_in_indent() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&align(3, allocate(create_lines()))),
+                    default_options()
+                ),
+                "foo\n     bar"
+            );
+        }
+
+        #[test]
+        fn format_indent_in_aligned_document() {
+            assert_eq!(
+                format_to_string(
+                    &align(3, &indent(allocate(create_lines()))),
+                    default_options()
+                ),
+                "foo\n     bar"
+            );
+        }
+
+        #[test]
+        fn format_aligned_document_with_tab() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&align(3, allocate(create_lines()))),
+                    FormatOptions::tab()
+                ),
+                "foo\n\t   bar"
+            );
+            assert_eq!(
+                format_to_string(
+                    &align(3, &indent(allocate(create_lines()))),
+                    FormatOptions::tab()
+                ),
+                "foo\n\t   bar"
+            );
+        }
+
+        #[test]
+        fn format_aligned_document_in_offside() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "foo".into(),
+                        offside(&align(2, &sequence(&[hard_line(), "bar".into()])), false),
+                    ]),
+                    default_options()
+                ),
+                "foo\n     bar"
+            );
+        }
+
+        #[test]
+        fn format_aligned_empty_line() {
+            assert_eq!(
+                format_to_string(
+                    &align(3, &sequence(&["foo".into(), hard_line(), hard_line()])),
+                    default_options()
+                ),
+                "foo\n\n"
+            );
+        }
+    }
+
+    mod dedent {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn format_dedented_document() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&sequence(&[
+                        hard_line(),
+                        "foo".into(),
+                        dedent(&sequence(&[hard_line(), "bar".into()])),
+                        hard_line(),
+                        "baz".into(),
+                    ])),
+                    default_options()
+                ),
+                "\n  foo\nbar\n  baz"
+            );
+        }
+
+        #[test]
+        fn format_dedented_document_without_indent() {
+            assert_eq!(
+                format_to_string(
+                    &align(
+                        1,
+                        &dedent(&sequence(&["foo".into(), hard_line(), "bar".into()]))
+                    ),
+                    default_options()
+                ),
+                "foo\n bar"
+            );
+        }
+
+        #[test]
+        fn format_document_dedented_to_root() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&indent(&align(
+                        1,
+                        &sequence(&[
+                            "foo".into(),
+                            dedent_to_root(&sequence(&[hard_line(), "#bar".into()]), false),
+                            hard_line(),
+                            "baz".into(),
+                        ])
+                    ))),
+                    default_options()
+                ),
+                "foo\n#bar\n     baz"
+            );
+        }
+
+        #[test]
+        fn format_document_dedented_to_offside_root() {
+            let document = |root: bool| {
+                sequence(allocate([
+                    "let x = ".into(),
+                    offside(
+                        allocate(indent(allocate(sequence(allocate([
+                            "foo".into(),
+                            hard_line(),
+                            "bar".into(),
+                            dedent_to_root(
+                                allocate(sequence(allocate([hard_line(), "baz".into()]))),
+                                root,
+                            ),
+                        ]))))),
+                        false,
+                    ),
+                ]))
+            };
+
+            assert_eq!(
+                format_to_string(&document(true), default_options()),
+                "let x = foo\n          bar\n        baz"
+            );
+            assert_eq!(
+                format_to_string(&document(false), default_options()),
+                "let x = foo\n          bar\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_document_dedented_to_offside_root_with_tab() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&sequence(&[
+                        "foo".into(),
+                        offside(
+                            &indent(&dedent_to_root(
+                                &sequence(&[hard_line(), "bar".into()]),
+                                true
+                            )),
+                            false
+                        ),
+                    ])),
+                    FormatOptions::tab().set_smart_tab(true)
+                ),
+                "foo\n\t  bar"
+            );
+        }
+    }
+
+    mod offside {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_group() -> Document<'static> {
+            sequence(allocate([
+                "foo".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    offside(
+                        allocate(r#break(allocate(sequence(allocate([
+                            "bar".into(),
+                            line(),
+                            "baz".into(),
+                        ]))))),
+                        false,
+                    ),
+                ])))),
+            ]))
+        }
+
+        #[test]
+        fn format_flat_group() {
+            assert_eq!(
+                format_to_string(&flatten(&create_group()), default_options().set_indent(2)),
+                indoc!(
+                    "
+                    foo bar
+                        baz
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_broken_group() {
+            assert_eq!(
+                format_to_string(&r#break(&create_group()), default_options().set_indent(2)),
+                indoc!(
+                    "
+                    foo
+                      bar
+                      baz
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        mod nested {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            fn create_groups(
+                inner: for<'a> fn(&'a Document<'a>) -> Document<'a>,
+            ) -> Document<'static> {
+                sequence(allocate([
+                    "foo".into(),
+                    indent(allocate(sequence(allocate([
+                        line(),
+                        offside(
+                            allocate(r#break(allocate(sequence(allocate([
+                                "bar".into(),
+                                line(),
+                                "baz".into(),
+                                line(),
+                                inner(allocate(sequence(allocate([
+                                    "qux".into(),
+                                    indent(allocate(sequence(allocate([
+                                        line(),
+                                        offside(
+                                            allocate(r#break(allocate(sequence(allocate([
+                                                "quux".into(),
+                                                line(),
+                                                "corge".into(),
+                                            ]))))),
+                                            false,
+                                        ),
+                                    ])))),
+                                ])))),
+                            ]))))),
+                            false,
+                        ),
+                    ])))),
+                ]))
+            }
+
+            #[test]
+            fn format_flat_outer_with_flat_inner() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&create_groups(flatten)),
+                        default_options().set_indent(2)
+                    ),
+                    indoc!(
+                        "
+                    foo bar
+                        baz
+                        qux quux
+                            corge
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_flat_outer_with_broken_inner() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&create_groups(r#break)),
+                        default_options().set_indent(2)
+                    ),
+                    indoc!(
+                        "
+                    foo bar
+                        baz
+                        qux
+                          quux
+                          corge
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_broken_outer_with_flat_inner() {
+                assert_eq!(
+                    format_to_string(
+                        &r#break(&create_groups(flatten)),
+                        default_options().set_indent(2)
+                    ),
+                    indoc!(
+                        "
+                    foo
+                      bar
+                      baz
+                      qux quux
+                          corge
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_broken_outer_with_broken_inner() {
+                assert_eq!(
+                    format_to_string(
+                        &r#break(&create_groups(r#break)),
+                        default_options().set_indent(2)
+                    ),
+                    indoc!(
+                        "
+                    foo
+                      bar
+                      baz
+                      qux
+                        quux
+                        corge
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_two_flat_groups() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&sequence(&[
+                            "qux".into(),
+                            line(),
+                            offside(
+                                &r#break(&sequence(&[
+                                    flatten(&create_group()),
+                                    line(),
+                                    flatten(&create_group())
+                                ])),
+                                false
+                            )
+                        ])),
+                        default_options().set_indent(1)
+                    ),
+                    indoc!(
+                        "
+                        qux foo bar
+                                baz
+                            foo bar
+                                baz
+                        "
+                    )
+                    .trim(),
+                );
+            }
+        }
+
+        mod soft {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            #[test]
+            fn format_less_indent() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&sequence(&[
+                            "a".into(),
+                            indent(&sequence(&[
+                                line(),
+                                offside(
+                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
+                                    true,
+                                ),
+                            ])),
+                        ])),
+                        default_options().set_indent(1)
+                    ),
+                    indoc!(
+                        "
+                    a b
+                      c
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_equal_indent() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&sequence(&[
+                            "a".into(),
+                            indent(&sequence(&[
+                                line(),
+                                offside(
+                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
+                                    true,
+                                ),
+                            ])),
+                        ])),
+                        default_options().set_indent(2)
+                    ),
+                    indoc!(
+                        "
+                    a b
+                      c
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_more_indent() {
+                assert_eq!(
+                    format_to_string(
+                        &flatten(&sequence(&[
+                            "a".into(),
+                            indent(&sequence(&[
+                                line(),
+                                offside(
+                                    &r#break(&sequence(&["b".into(), line(), "c".into(),])),
+                                    true,
+                                ),
+                            ])),
+                        ])),
+                        default_options().set_indent(3)
+                    ),
+                    indoc!(
+                        "
+                    a b
+                       c
+                    "
+                    )
+                    .trim(),
+                );
+            }
+
+            #[test]
+            fn format_less_indent_with_tab() {
+                assert_eq!(
+                    format_to_string(
+                        &indent(&indent(&sequence(&[
+                            "x".into(),
+                            offside(&sequence(&["y".into(), line(), "z".into()]), true),
+                        ]))),
+                        FormatOptions::tab().set_tab_width(4)
+                    ),
+                    "xy\n\t\tz"
+                );
+            }
+        }
+    }
+
+    mod max_width {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_group() -> Document<'static> {
+            group(allocate(sequence(allocate([
+                "{".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    "foo".into(),
+                    line(),
+                    "bar".into(),
+                ])))),
+                line(),
+                "}".into(),
+            ]))))
+        }
+
+        #[test]
+        fn format_fitting_group() {
+            assert_eq!(
+                format_to_string(&create_group(), default_options().set_max_width(11)),
+                "{ foo bar }"
+            );
+        }
+
+        #[test]
+        fn format_overflowing_group() {
+            assert_eq!(
+                format_to_string(&create_group(), default_options().set_max_width(10)),
+                indoc!(
+                    "
+                    {
+                      foo
+                      bar
+                    }
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_group_after_column() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&["foo ".into(), create_group()]),
+                    default_options().set_max_width(14)
+                ),
+                indoc!(
+                    "
+                    foo {
+                      foo
+                      bar
+                    }
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_group_with_unlimited_width() {
+            assert_eq!(
+                format_to_string(&create_group(), default_options()),
+                "{ foo bar }"
+            );
+        }
+
+        #[test]
+        fn format_group_with_broken_document() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&["foo".into(), line(), r#break(&"bar".into())])),
+                    default_options()
+                ),
+                "foo\nbar"
+            );
+        }
+
+        #[test]
+        fn format_group_in_flat_document() {
+            assert_eq!(
+                format_to_string(
+                    &flatten(&create_group()),
+                    default_options().set_max_width(10)
+                ),
+                "{ foo bar }"
+            );
+        }
+
+        #[test]
+        fn format_nested_groups() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&[
+                        "foo".into(),
+                        indent(&sequence(&[
+                            line(),
+                            group(&sequence(&["bar".into(), line(), "baz".into()])),
+                        ])),
+                    ])),
+                    default_options().set_max_width(10)
+                ),
+                indoc!(
+                    "
+                    foo
+                      bar baz
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_group_until_first_newline() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        r#break(&sequence(&["bar".into(), line(), "bazqux".into()])),
+                    ])),
+                    default_options().set_max_width(7)
+                ),
+                indoc!(
+                    "
+                    foo
+                    bar
+                    bazqux
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_group_with_trailing_content() {
+            let document = sequence(allocate([
+                group(allocate(sequence(allocate([
+                    "aaaa".into(),
+                    line(),
+                    "bbbb".into(),
+                ])))),
+                ";;;;;;".into(),
+            ]));
+
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(15)),
+                "aaaa bbbb;;;;;;"
+            );
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(9)),
+                "aaaa\nbbbb;;;;;;"
+            );
+        }
+
+        #[test]
+        fn format_group_with_trailing_content_after_newline() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        group(&sequence(&["aaaa".into(), line(), "bbbb".into()])),
+                        hard_line(),
+                        ";;;;;;;;;;;;".into(),
+                    ]),
+                    default_options().set_max_width(9)
+                ),
+                "aaaa bbbb\n;;;;;;;;;;;;"
+            );
+        }
+
+        #[test]
+        fn format_group_with_trailing_line_in_broken_parent() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        group(&sequence(&["aaaa".into(), line(), "bbbb".into()])),
+                        line(),
+                        ";;;;;;;;;;;;".into(),
+                    ]),
+                    default_options().set_max_width(9)
+                ),
+                "aaaa bbbb\n;;;;;;;;;;;;"
+            );
+        }
+    }
+
+    mod fill {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_fill() -> Document<'static> {
+            fill(allocate([
+                "foo".into(),
+                line(),
+                "bar".into(),
+                line(),
+                "baz".into(),
+                line(),
+                "qux".into(),
+            ]))
+        }
+
+        #[test]
+        fn format_empty_fill() {
+            assert_eq!(format_to_string(&fill(&[]), default_options()), "");
+        }
+
+        #[test]
+        fn format_fitting_fill() {
+            assert_eq!(
+                format_to_string(&create_fill(), default_options()),
+                "foo bar baz qux"
+            );
+        }
+
+        #[test]
+        fn format_overflowing_fill() {
+            assert_eq!(
+                format_to_string(&create_fill(), default_options().set_max_width(11)),
+                "foo bar baz\nqux"
+            );
+            assert_eq!(
+                format_to_string(&create_fill(), default_options().set_max_width(10)),
+                "foo bar\nbaz qux"
+            );
+            assert_eq!(
+                format_to_string(&create_fill(), default_options().set_max_width(3)),
+                "foo\nbar\nbaz\nqux"
+            );
+        }
+
+        #[test]
+        fn format_fill_in_flat_document() {
+            assert_eq!(
+                format_to_string(&flatten(&create_fill()), default_options().set_max_width(3)),
+                "foo bar baz qux"
+            );
+        }
+
+        #[test]
+        fn format_indented_fill() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "[".into(),
+                        indent(&sequence(&[soft_line(), create_fill()])),
+                        soft_line(),
+                        "]".into(),
+                    ]),
+                    default_options().set_max_width(9)
+                ),
+                indoc!(
+                    "
+                    [
+                      foo bar
+                      baz qux
+                    ]
+                    "
+                )
+                .trim(),
+            );
+        }
+
+        #[test]
+        fn format_fill_with_overflowing_content() {
+            let document = fill(allocate([
+                "foo".into(),
+                line(),
+                sequence(allocate(["bar".into(), line(), "baz".into()])),
+            ]));
+
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(7)),
+                "foo\nbar baz"
+            );
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(6)),
+                "foo\nbar\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_fill_with_hard_line() {
+            assert_eq!(
+                format_to_string(
+                    &fill(&["foo".into(), hard_line(), "bar".into()]),
+                    default_options()
+                ),
+                "foo\nbar"
+            );
+        }
+
+        #[test]
+        fn format_group_after_fill() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[create_fill(), group(&sequence(&[line(), "quux".into()])),]),
+                    default_options().set_max_width(16)
+                ),
+                "foo bar baz qux\nquux"
+            );
+        }
+    }
+
+    mod smart_tab {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_document() -> Document<'static> {
+            sequence(allocate([
+                "{".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    "foo ".into(),
+                    offside(
+                        allocate(sequence(allocate([
+                            "bar".into(),
+                            line(),
+                            "baz".into(),
+                            indent(allocate(sequence(allocate([line(), "qux".into()])))),
+                        ]))),
+                        false,
+                    ),
+                ])))),
+                line(),
+                "}".into(),
+            ]))
+        }
+
+        #[test]
+        fn format_without_smart_tab() {
+            assert_eq!(
+                format_to_string(&create_document(), FormatOptions::tab()),
+                "{\n\tfoo bar\n\t\t\t\t\tbaz\n\t\t\t\t\t\tqux\n}"
+            );
+        }
+
+        #[test]
+        fn format_with_smart_tab() {
+            assert_eq!(
+                format_to_string(&create_document(), FormatOptions::tab().set_smart_tab(true)),
+                "{\n\tfoo bar\n\t    baz\n\t\t    qux\n}"
+            );
+        }
+
+        #[test]
+        fn format_with_spaces() {
+            let options = default_options().set_indent(4);
+
+            assert_eq!(
+                format_to_string(&create_document(), options.set_smart_tab(true)),
+                format_to_string(&create_document(), options)
+            );
+        }
+
+        #[test]
+        fn format_soft_offside() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        "foo ".into(),
+                        offside(
+                            &sequence(&[
+                                "bar".into(),
+                                indent(&sequence(&[
+                                    line(),
+                                    offside(&sequence(&["baz".into(), line(), "qux".into()]), true),
+                                ])),
+                            ]),
+                            false,
+                        ),
+                    ]),
+                    FormatOptions::tab().set_smart_tab(true)
+                ),
+                "foo bar\n\t    baz\n\t    qux"
+            );
+        }
+    }
+
+    mod tab_width {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_document() -> Document<'static> {
+            sequence(allocate([
+                "{".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    "foobar ".into(),
+                    offside(
+                        allocate(sequence(allocate(["bar".into(), line(), "baz".into()]))),
+                        false,
+                    ),
+                ])))),
+            ]))
+        }
+
+        #[test]
+        fn format_offside() {
+            assert_eq!(
+                format_to_string(&create_document(), FormatOptions::tab().set_tab_width(4)),
+                "{\n\tfoobar bar\n\t\t   baz"
+            );
+        }
+
+        #[test]
+        fn format_offside_with_smart_tab() {
+            assert_eq!(
+                format_to_string(
+                    &create_document(),
+                    FormatOptions::tab().set_tab_width(4).set_smart_tab(true)
+                ),
+                "{\n\tfoobar bar\n\t       baz"
+            );
+        }
+
+        #[test]
+        fn format_offside_after_tab() {
+            assert_eq!(
+                format_to_string(
+                    &flatten(&sequence(&[
+                        "a\tb".into(),
+                        line(),
+                        offside(
+                            &r#break(&sequence(&["c".into(), line(), "d".into()])),
+                            false
+                        ),
+                    ])),
+                    default_options().set_tab_width(8)
+                ),
+                "a\tb c\n          d"
+            );
+        }
+
+        #[test]
+        fn format_zero_tab_width() {
+            assert_eq!(
+                format_to_string(&"a\tb".into(), default_options().set_tab_width(0)),
+                "a\tb"
+            );
+            assert_eq!(
+                format_to_string(&create_document(), FormatOptions::tab().set_tab_width(0)),
+                format_to_string(&create_document(), FormatOptions::tab())
+            );
+        }
+
+        #[test]
+        fn format_zero_width_space() {
+            assert_eq!(
+                format_to_string(
+                    &create_document(),
+                    FormatOptions::new(1)
+                        .set_space('\u{200b}')
+                        .set_width_unit(WidthUnit::Display)
+                ),
+                "{\n\u{200b}foobar bar\n\u{200b}\u{200b}\u{200b}\u{200b}\u{200b}\u{200b}\u{200b}baz"
+            );
+        }
+
+        #[test]
+        fn format_group_with_tab() {
+            let document = group(allocate(sequence(allocate([
+                "\tfoo".into(),
+                line(),
+                "bar".into(),
+            ]))));
+
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(11)),
+                "\tfoo bar"
+            );
+            assert_eq!(
+                format_to_string(
+                    &document,
+                    default_options().set_tab_width(4).set_max_width(10)
+                ),
+                "\tfoo\nbar"
+            );
+        }
+    }
+
+    #[test]
+    fn format_deep_document() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut document = "foo".into();
+
+        for index in 0..1_000_000 {
+            document = match index % 3 {
+                0 => builder.sequence([document, "".into()]),
+                1 => builder.indent(document),
+                _ => builder.flatten(document),
+            };
+        }
+
+        assert_eq!(format_to_string(&document, default_options()), "foo");
+    }
+
+    mod prefix {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_lines() -> &'static Document<'static> {
+            allocate(sequence(allocate([
+                "foo".into(),
+                hard_line(),
+                "bar".into(),
+            ])))
+        }
+
+        fn create_prefix<'a>(string: &'a str, document: &'a Document<'a>) -> Document<'a> {
+            sequence(Box::leak(Box::new([
+                string.into(),
+                prefix(string, document),
+            ])))
+        }
+
+        #[test]
+        fn format_prefix() {
+            assert_eq!(
+                format_to_string(&prefix("// ", create_lines()), default_options()),
+                "foo\n// bar"
+            );
+            assert_eq!(
+                format_to_string(&create_prefix("// ", create_lines()), default_options()),
+                "// foo\n// bar"
+            );
+        }
+
+        #[test]
+        fn format_prefix_in_indent() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&sequence(&[
+                        hard_line(),
+                        create_prefix("// ", create_lines())
+                    ])),
+                    default_options()
+                ),
+                "\n  // foo\n  // bar"
+            );
+        }
+
+        #[test]
+        fn format_indent_in_prefix() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "// ",
+                        &sequence(&[
+                            "foo".into(),
+                            indent(&sequence(&[hard_line(), "bar".into()])),
+                            hard_line(),
+                            "baz".into(),
+                        ])
+                    ),
+                    default_options()
+                ),
+                "// foo\n//   bar\n// baz"
+            );
+        }
+
+        #[test]
+        fn format_dedent_in_prefix() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&create_prefix(
+                        "// ",
+                        &dedent_to_root(
+                            &sequence(&["foo".into(), hard_line(), "bar".into()]),
+                            false
+                        )
+                    )),
+                    default_options()
+                ),
+                "// foo\n  // bar"
+            );
+        }
+
+        #[test]
+        fn format_empty_line() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "> ",
+                        &sequence(&["foo".into(), hard_line(), hard_line(), "bar".into()])
+                    ),
+                    default_options()
+                ),
+                "> foo\n>\n> bar"
+            );
+        }
+
+        #[test]
+        fn format_last_empty_line() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix("# ", &sequence(&["foo".into(), hard_line()])),
+                    default_options()
+                ),
+                "# foo\n#"
+            );
+        }
+
+        #[test]
+        fn format_nested_prefixes() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "> ",
+                        &sequence(&[
+                            create_prefix("> ", create_lines()),
+                            hard_line(),
+                            "baz".into(),
+                        ])
+                    ),
+                    default_options()
+                ),
+                "> > foo\n> > bar\n> baz"
+            );
+        }
+
+        #[test]
+        fn format_nested_empty_line() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "> ",
+                        &create_prefix(
+                            "> ",
+                            &sequence(&["foo".into(), hard_line(), hard_line(), "bar".into()])
+                        ),
+                    ),
+                    default_options()
+                ),
+                "> > foo\n> >\n> > bar"
+            );
+        }
+
+        #[test]
+        fn format_offside_in_prefix() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "// ",
+                        &sequence(&[
+                            "foo(".into(),
+                            offside(&sequence(&["bar".into(), hard_line(), "baz".into()]), false),
+                        ])
+                    ),
+                    default_options()
+                ),
+                "// foo(bar\n//     baz"
+            );
+        }
+
+        #[test]
+        fn format_group_in_prefix() {
+            let document = create_prefix(
+                "// ",
+                allocate(group(allocate(sequence(allocate([
+                    "foo".into(),
+                    line(),
+                    "bar".into(),
+                ]))))),
+            );
+
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(10)),
+                "// foo bar"
+            );
+            assert_eq!(
+                format_to_string(&document, default_options().set_max_width(9)),
+                "// foo\n// bar"
+            );
+        }
+
+        #[test]
+        fn format_line_suffix_in_prefix() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "// ",
+                        &sequence(&["foo".into(), line_suffix(" baz"), hard_line(), "bar".into(),])
+                    ),
+                    default_options()
+                ),
+                "// foo baz\n// bar"
+            );
+        }
+
+        #[test]
+        fn format_verbatim_in_prefix() {
+            assert_eq!(
+                format_to_string(
+                    &create_prefix(
+                        "> ",
+                        &sequence(&["a".into(), hard_line(), verbatim("x\ny", false)])
+                    ),
+                    default_options()
+                ),
+                "> a\n> x\n> y"
+            );
+        }
+
+        #[test]
+        fn format_mark_in_prefix() {
+            assert_eq!(
+                format_with_source_map(
+                    &create_prefix(
+                        "# ",
+                        &sequence(&[
+                            "foo".into(),
+                            hard_line(),
+                            mark(0),
+                            hard_line(),
+                            mark(1),
+                            "bar".into(),
+                        ])
+                    ),
+                    &mut String::new(),
+                    default_options()
+                )
+                .unwrap(),
+                [
+                    Mapping::new(0, Position::new(7, 1, 1)),
+                    Mapping::new(1, Position::new(10, 2, 2)),
+                ]
+            );
+        }
+    }
+
+    mod table {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_table(alignments: &'static [ColumnAlignment]) -> Document<'static> {
+            table(
+                allocate([
+                    allocate(["x".into(), " = ".into(), "1".into()]).as_slice(),
+                    allocate(["foo".into(), " = ".into(), "42".into()]),
+                ]),
+                alignments,
+            )
+        }
+
+        #[test]
+        fn format_empty_table() {
+            assert_eq!(format_to_string(&table(&[], &[]), default_options()), "");
+        }
+
+        #[test]
+        fn format_left_aligned_table() {
+            assert_eq!(
+                format_to_string(&create_table(&[]), default_options()),
+                "x   = 1\nfoo = 42"
+            );
+        }
+
+        #[test]
+        fn format_right_aligned_table() {
+            assert_eq!(
+                format_to_string(
+                    &create_table(&[
+                        ColumnAlignment::Left,
+                        ColumnAlignment::Left,
+                        ColumnAlignment::Right
+                    ]),
+                    default_options()
+                ),
+                "x   =  1\nfoo = 42"
+            );
+        }
+
+        #[test]
+        fn format_indented_table() {
+            assert_eq!(
+                format_to_string(
+                    &indent(&sequence(&[
+                        hard_line(),
+                        table(&[&["1".into()], &["100".into()]], &[ColumnAlignment::Right]),
+                    ])),
+                    default_options()
+                ),
+                "\n    1\n  100"
+            );
+        }
+
+        #[test]
+        fn format_rows_with_different_lengths() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[&["a".into(), "b".into(), "c".into()], &["dd".into()]],
+                        &[]
+                    ),
+                    default_options()
+                ),
+                "a bc\ndd"
+            );
+        }
+
+        #[test]
+        fn format_flat_cells() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[
+                            &[sequence(&["a".into(), line(), "b".into()]), "|".into()],
+                            &["ccc".into(), "|".into()],
+                        ],
+                        &[]
+                    ),
+                    default_options()
+                ),
+                "a b|\nccc|"
+            );
+        }
+
+        #[test]
+        fn format_broken_cell() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[
+                            &[
+                                "foo".into(),
+                                " = ".into(),
+                                r#break(&sequence(&[
+                                    "{".into(),
+                                    indent(&sequence(&[line(), "bar".into()])),
+                                    line(),
+                                    "}".into(),
+                                ])),
+                            ],
+                            &["x".into(), " = ".into(), "1".into()],
+                        ],
+                        &[]
+                    ),
+                    default_options()
+                ),
+                "foo = {\n  bar\n}\nx   = 1"
+            );
+        }
+
+        #[test]
+        fn format_broken_cell_in_middle_of_row() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[
+                            &[
+                                sequence(&["a".into(), hard_line(), "b".into()]),
+                                " = ".into(),
+                                "1".into(),
+                            ],
+                            &["foo".into(), " = ".into(), "2".into()],
+                        ],
+                        &[ColumnAlignment::Right]
+                    ),
+                    default_options()
+                ),
+                "a\nb = 1\nfoo = 2"
+            );
+        }
+
+        #[test]
+        fn format_cells_after_broken_cell() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[
+                            &[
+                                sequence(&["a".into(), hard_line(), "b".into()]),
+                                " == ".into(),
+                                "1".into(),
+                            ],
+                            &["foo".into(), " = ".into(), "2".into()],
+                        ],
+                        &[]
+                    ),
+                    default_options()
+                ),
+                "a\nb == 1\nfoo = 2"
+            );
+        }
+
+        #[test]
+        fn format_overflowing_cell() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[&["foo".into(), "bar".into()], &["x".into(), "y".into()]],
+                        &[]
+                    ),
+                    default_options().set_max_width(2)
+                ),
+                "foobar\nxy"
+            );
+        }
+
+        #[test]
+        fn format_table_in_group() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&[
+                        "{".into(),
+                        line(),
+                        create_table(&[]),
+                        line(),
+                        "}".into()
+                    ])),
+                    default_options()
+                ),
+                "{\nx   = 1\nfoo = 42\n}"
+            );
+        }
+
+        #[test]
+        fn format_table_with_display_width() {
+            assert_eq!(
+                format_to_string(
+                    &table(
+                        &[&["あ".into(), "|".into()], &["abc".into(), "|".into()]],
+                        &[]
+                    ),
+                    default_options().set_width_unit(WidthUnit::Display)
+                ),
+                "あ |\nabc|"
+            );
+        }
+    }
+
+    mod verbatim {
+        use super::*;
+        use crate::LineEnding;
+        use pretty_assertions::assert_eq;
+
+        fn create_comment(indent: bool) -> Document<'static> {
+            super::indent(allocate(sequence(allocate([
+                "foo".into(),
+                hard_line(),
+                verbatim("/*\n * bar\n */", indent),
+            ]))))
+        }
+
+        #[test]
+        fn format_single_line() {
+            assert_eq!(
+                format_to_string(&verbatim("foo", false), default_options()),
+                "foo"
+            );
+        }
+
+        #[test]
+        fn format_multiple_lines() {
+            assert_eq!(
+                format_to_string(&create_comment(false), default_options()),
+                "foo\n  /*\n * bar\n */"
+            );
+        }
+
+        #[test]
+        fn format_indented_lines() {
+            assert_eq!(
+                format_to_string(&create_comment(true), default_options()),
+                "foo\n  /*\n   * bar\n   */"
+            );
+        }
+
+        #[test]
+        fn format_empty_lines() {
+            assert_eq!(
+                format_to_string(&indent(&verbatim("foo\n\nbar\n", true)), default_options()),
+                "foo\n\n  bar\n"
+            );
+        }
+
+        #[test]
+        fn format_offside_after_lines() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        verbatim("foo\nbarbaz", false),
+                        offside(&sequence(&["(".into(), hard_line(), ")".into()]), false),
+                    ]),
+                    default_options()
+                ),
+                "foo\nbarbaz(\n      )"
+            );
+        }
+
+        #[test]
+        fn format_line_suffix() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        line_suffix(" // baz"),
+                        verbatim("foo\nbar", false),
+                        hard_line(),
+                    ]),
+                    default_options()
+                ),
+                "foo\nbar // baz\n"
+            );
+        }
+
+        #[test]
+        fn format_line_ending() {
+            assert_eq!(
+                format_to_string(
+                    &verbatim("foo\r\nbar\nbaz", false),
+                    default_options().set_line_ending(LineEnding::CrLf)
+                ),
+                "foo\r\nbar\r\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_trailing_whitespace() {
+            assert_eq!(
+                format_to_string(
+                    &verbatim("foo  \nbar ", false),
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                "foo\nbar"
+            );
+        }
+
+        #[test]
+        fn format_group() {
+            assert_eq!(
+                format_to_string(
+                    &group(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        verbatim("bar\nbaz", false)
+                    ])),
+                    default_options()
+                ),
+                "foo\nbar\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_fill() {
+            assert_eq!(
+                format_to_string(
+                    &fill(&[
+                        "foo".into(),
+                        line(),
+                        verbatim("bar\nbazqux", false),
+                        line(),
+                        "quux".into(),
+                    ]),
+                    default_options().set_max_width(7)
+                ),
+                "foo bar\nbazqux\nquux"
+            );
+        }
+
+        #[test]
+        fn format_mark() {
+            assert_eq!(
+                format_with_source_map(
+                    &sequence(&[verbatim("foo\nbar", false), mark(0)]),
+                    &mut String::new(),
+                    default_options()
+                )
+                .unwrap(),
+                [Mapping::new(0, Position::new(7, 1, 3))]
+            );
+        }
+    }
+
+    mod source_map {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn format_to_mappings(document: &Document, options: FormatOptions) -> Vec<Mapping> {
+            format_with_source_map(document, &mut String::new(), options).unwrap()
+        }
+
+        #[test]
+        fn format_mark() {
+            assert_eq!(
+                format_to_mappings(
+                    &sequence(&[
+                        mark(0),
+                        "foo".into(),
+                        mark(1),
+                        line(),
+                        "bar".into(),
+                        mark(2)
+                    ]),
+                    default_options()
+                ),
+                [
+                    Mapping::new(0, Position::new(0, 0, 0)),
+                    Mapping::new(1, Position::new(3, 0, 3)),
+                    Mapping::new(2, Position::new(7, 1, 3)),
+                ]
+            );
+        }
+
+        #[test]
+        fn format_mark_with_indent() {
+            assert_eq!(
+                format_to_mappings(
+                    &indent(&sequence(&["foo".into(), line(), mark(0), "bar".into()])),
+                    default_options()
+                ),
+                [Mapping::new(0, Position::new(6, 1, 2))]
+            );
+        }
+
+        #[test]
+        fn format_mark_on_empty_line() {
+            assert_eq!(
+                format_to_mappings(
+                    &indent(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        mark(0),
+                        line(),
+                        "bar".into()
+                    ])),
+                    default_options()
+                ),
+                [Mapping::new(0, Position::new(4, 1, 0))]
+            );
+        }
+
+        #[test]
+        fn format_mark_with_line_suffix() {
+            assert_eq!(
+                format_to_mappings(
+                    &sequence(&["foo".into(), line_suffix(" // bar"), mark(0), line()]),
+                    default_options()
+                ),
+                [Mapping::new(0, Position::new(3, 0, 3))]
+            );
+        }
+
+        #[test]
+        fn format_mark_in_trailing_whitespace() {
+            let document = sequence(allocate([
+                "foo ".into(),
+                mark(0),
+                line(),
+                "bar".into(),
+                " ".into(),
+                mark(1),
+            ]));
+
+            assert_eq!(
+                format_to_mappings(&document, default_options()),
+                [
+                    Mapping::new(0, Position::new(4, 0, 4)),
+                    Mapping::new(1, Position::new(9, 1, 4)),
+                ]
+            );
+            assert_eq!(
+                format_to_mappings(
+                    &document,
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                [
+                    Mapping::new(0, Position::new(3, 0, 3)),
+                    Mapping::new(1, Position::new(7, 1, 3)),
+                ]
+            );
+        }
+
+        #[test]
+        fn format_mark_with_display_width() {
+            assert_eq!(
+                format_to_mappings(
+                    &sequence(&["日本".into(), mark(0)]),
+                    default_options().set_width_unit(WidthUnit::Display)
+                ),
+                [Mapping::new(0, Position::new(6, 0, 4))]
+            );
+        }
+    }
+
+    mod cursor {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn format_to_cursor(document: &Document, options: FormatOptions) -> Option<Position> {
+            format_with_cursor(document, &mut String::new(), options).unwrap()
+        }
+
+        #[test]
+        fn format_without_cursor() {
+            assert_eq!(format_to_cursor(&"foo".into(), default_options()), None);
+        }
+
+        #[test]
+        fn format_cursor() {
+            assert_eq!(
+                format_to_cursor(
+                    &indent(&sequence(&[
+                        "foo".into(),
+                        line(),
+                        "b".into(),
+                        cursor(),
+                        "ar".into()
+                    ])),
+                    default_options()
+                ),
+                Some(Position::new(7, 1, 3))
+            );
+        }
+
+        #[test]
+        fn format_cursor_in_trailing_whitespace() {
+            assert_eq!(
+                format_to_cursor(
+                    &sequence(&["foo ".into(), cursor(), line(), "bar".into()]),
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                Some(Position::new(3, 0, 3))
+            );
+        }
+
+        #[test]
+        fn format_cursor_on_empty_line() {
+            assert_eq!(
+                format_to_cursor(
+                    &indent(&sequence(&["foo".into(), line(), cursor()])),
+                    default_options()
+                ),
+                Some(Position::new(4, 1, 0))
+            );
+        }
+    }
+
+    mod range {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_document() -> Document<'static> {
+            sequence(allocate([
+                "{".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    "foo".into(),
+                    line(),
+                    "b".into(),
+                    mark(0),
+                    "ar".into(),
+                    indent(allocate(sequence(allocate([
+                        line(),
+                        "baz".into(),
+                        mark(1),
+                    ])))),
+                    line(),
+                    "qux".into(),
+                ])))),
+                line(),
+                "}".into(),
+            ]))
+        }
+
+        #[test]
+        fn format_lines() {
+            assert_eq!(
+                format_range(&create_document(), 0, 1, default_options()).unwrap(),
+                Some(FormattedRange::new("  bar\n    baz".into(), 8..21))
+            );
+        }
+
+        #[test]
+        fn format_reversed_marks() {
+            assert_eq!(
+                format_range(&create_document(), 1, 0, default_options()).unwrap(),
+                Some(FormattedRange::new("  bar\n    baz".into(), 8..21))
+            );
+        }
+
+        #[test]
+        fn format_line() {
+            assert_eq!(
+                format_range(&create_document(), 0, 0, default_options()).unwrap(),
+                Some(FormattedRange::new("  bar".into(), 8..13))
+            );
+        }
+
+        #[test]
+        fn format_line_with_crlf() {
+            assert_eq!(
+                format_range(
+                    &create_document(),
+                    0,
+                    0,
+                    default_options().set_line_ending(crate::LineEnding::CrLf)
+                )
+                .unwrap(),
+                Some(FormattedRange::new("  bar".into(), 10..15))
+            );
+        }
+
+        #[test]
+        fn format_missing_mark() {
+            assert_eq!(
+                format_range(&create_document(), 0, 2, default_options()).unwrap(),
+                None
+            );
+        }
+    }
+
+    mod trailing_whitespace {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_document() -> Document<'static> {
+            sequence(allocate([
+                "foo ".into(),
+                line(),
+                "bar".into(),
+                indent(allocate(sequence(allocate([
+                    line(),
+                    line(),
+                    "baz\t".into(),
+                    line_suffix(" // qux "),
+                    line(),
+                    " ".into(),
+                ])))),
+            ]))
+        }
+
+        #[test]
+        fn format_without_trimming() {
+            assert_eq!(
+                format_to_string(&create_document(), default_options()),
+                "foo \nbar\n\n  baz\t // qux \n   "
+            );
+        }
+
+        #[test]
+        fn format_with_trimming() {
+            assert_eq!(
+                format_to_string(
+                    &create_document(),
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                "foo\nbar\n\n  baz\t // qux\n"
+            );
+        }
+
+        #[test]
+        fn format_flat_line_before_break() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&[
+                        flatten(&sequence(&["foo".into(), line(), empty()])),
+                        line(),
+                        "bar".into(),
+                    ]),
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                "foo\nbar"
+            );
+        }
+
+        #[test]
+        fn format_crlf() {
+            assert_eq!(
+                format_to_string(
+                    &sequence(&["foo ".into(), line(), "bar".into()]),
+                    default_options()
+                        .set_trim_trailing_whitespace(true)
+                        .set_line_ending(crate::LineEnding::CrLf)
+                ),
+                "foo\r\nbar"
+            );
+        }
+
+        #[test]
+        fn format_without_trailing_whitespace() {
+            let document = create_group();
+
+            assert_eq!(
+                format_to_string(
+                    &document,
+                    default_options().set_trim_trailing_whitespace(true)
+                ),
+                format_to_string(&document, default_options())
+            );
+        }
+    }
+
+    mod line_ending {
+        use super::*;
+        use crate::LineEnding;
+        use pretty_assertions::assert_eq;
+
+        fn create_document() -> Document<'static> {
+            sequence(allocate([
+                "foo".into(),
+                line_suffix(" // foo"),
+                line(),
+                "bar".into(),
+                hard_line(),
+                "baz".into(),
+            ]))
+        }
+
+        #[test]
+        fn format_lf() {
+            assert_eq!(
+                format_to_string(&create_document(), default_options()),
+                "foo // foo\nbar\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_crlf() {
+            assert_eq!(
+                format_to_string(
+                    &create_document(),
+                    default_options().set_line_ending(LineEnding::CrLf)
+                ),
+                "foo // foo\r\nbar\r\nbaz"
+            );
+        }
+
+        #[test]
+        fn format_cr() {
+            assert_eq!(
+                format_to_string(
+                    &create_document(),
+                    default_options().set_line_ending(LineEnding::Cr)
+                ),
+                "foo // foo\rbar\rbaz"
+            );
+        }
+    }
+
+    mod width_unit {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn create_offside(string: &'static str) -> Document<'static> {
+            flatten(allocate(sequence(allocate([
+                string.into(),
+                line(),
+                offside(
+                    allocate(r#break(allocate(sequence(allocate([
+                        "foo".into(),
+                        line(),
+                        "bar".into(),
+                    ]))))),
+                    false,
+                ),
+            ]))))
+        }
+
+        #[test]
+        fn measure_string() {
+            for (string, byte, char, display) in [
+                ("foo", 3, 3, 3),
+                ("日本語", 9, 3, 6),
+                ("e\u{301}", 3, 2, 1),
+                ("🦀", 4, 1, 2),
+            ] {
+                assert_eq!(measure(string, WidthUnit::Byte), byte);
+                assert_eq!(measure(string, WidthUnit::Char), char);
+                assert_eq!(measure(string, WidthUnit::Display), display);
+            }
+        }
+
+        #[test]
+        fn format_offside_with_bytes() {
+            assert_eq!(
+                format_to_string(&create_offside("日本"), default_options()),
+                "日本 foo\n       bar"
+            );
+        }
+
+        #[test]
+        fn format_offside_with_chars() {
+            assert_eq!(
+                format_to_string(
+                    &create_offside("日本"),
+                    default_options().set_width_unit(WidthUnit::Char)
+                ),
+                "日本 foo\n   bar"
+            );
+        }
+
+        #[test]
+        fn format_offside_with_display_width() {
+            assert_eq!(
+                format_to_string(
+                    &create_offside("日本"),
+                    default_options().set_width_unit(WidthUnit::Display)
+                ),
+                "日本 foo\n     bar"
+            );
+        }
+
+        #[test]
+        fn format_group_with_display_width() {
+            let document = group(allocate(sequence(allocate([
+                "日本".into(),
+                line(),
+                "語".into(),
+            ]))));
+            let options = default_options().set_width_unit(WidthUnit::Display);
+
+            assert_eq!(
+                format_to_string(&document, options.set_max_width(7)),
+                "日本 語"
+            );
+            assert_eq!(
+                format_to_string(&document, options.set_max_width(6)),
+                "日本\n語"
+            );
+        }
+    }
+
     mod space {
         use super::*;
         use pretty_assertions::assert_eq;
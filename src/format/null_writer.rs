@@ -0,0 +1,11 @@
+use core::fmt::{self, Write};
+
+// A writer discarding everything written to it.
+#[derive(Debug, Default)]
+pub struct NullWriter;
+
+impl Write for NullWriter {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
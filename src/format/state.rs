@@ -1,7 +1,11 @@
 #[derive(Clone, Copy, Debug)]
 pub struct State {
     indent: usize,
+    alignment: usize,
     broken: bool,
+    root_indent: usize,
+    root_alignment: usize,
+    prefix: Option<usize>,
 }
 
 impl State {
@@ -9,24 +13,66 @@ impl State {
         self.indent
     }
 
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
     pub fn broken(&self) -> bool {
         self.broken
     }
 
+    pub fn root_indent(&self) -> usize {
+        self.root_indent
+    }
+
+    pub fn root_alignment(&self) -> usize {
+        self.root_alignment
+    }
+
+    pub fn prefix(&self) -> Option<usize> {
+        self.prefix
+    }
+
     pub fn set_indent(self, indent: usize) -> Self {
         Self { indent, ..self }
     }
 
+    pub fn set_alignment(self, alignment: usize) -> Self {
+        Self { alignment, ..self }
+    }
+
     pub fn set_broken(self, broken: bool) -> Self {
         Self { broken, ..self }
     }
+
+    pub fn set_root_indent(self, root_indent: usize) -> Self {
+        Self {
+            root_indent,
+            ..self
+        }
+    }
+
+    pub fn set_root_alignment(self, root_alignment: usize) -> Self {
+        Self {
+            root_alignment,
+            ..self
+        }
+    }
+
+    pub fn set_prefix(self, prefix: Option<usize>) -> Self {
+        Self { prefix, ..self }
+    }
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
             indent: 0,
+            alignment: 0,
             broken: true,
+            root_indent: 0,
+            root_alignment: 0,
+            prefix: None,
         }
     }
 }
@@ -0,0 +1,15 @@
+use crate::Document;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Task<'a> {
+    Document(&'a Document<'a>),
+    // Contents and separators of a fill starting with a content.
+    Fill(&'a [Document<'a>]),
+    // Separators and contents of a fill starting with a separator.
+    FillSeparator(&'a [Document<'a>]),
+    // A column of line suffixes to restore after a block of aligned line
+    // suffixes.
+    LineSuffixColumn(Option<usize>),
+    // Padding spaces.
+    Padding(usize),
+}
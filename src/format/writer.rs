@@ -0,0 +1,92 @@
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+// A writer trimming trailing whitespace on every line optionally.
+#[derive(Debug)]
+pub struct Writer<'a, W: Write> {
+    writer: &'a mut W,
+    trim: bool,
+    whitespace: String,
+    offset: usize,
+}
+
+impl<'a, W: Write> Writer<'a, W> {
+    pub fn new(writer: &'a mut W, trim: bool) -> Self {
+        Self {
+            writer,
+            trim,
+            whitespace: Default::default(),
+            offset: 0,
+        }
+    }
+
+    // Returns `true` if trailing whitespace is trimmed.
+    pub fn trim(&self) -> bool {
+        self.trim
+    }
+
+    // Returns a part of a string to be written even at the end of a line.
+    pub fn trim_end<'b>(&self, string: &'b str) -> &'b str {
+        if self.trim {
+            string.trim_end_matches(is_whitespace)
+        } else {
+            string
+        }
+    }
+
+    // Returns a byte offset including pending whitespace.
+    pub fn offset(&self) -> usize {
+        self.offset + self.whitespace.len()
+    }
+
+    // Returns a byte offset excluding pending whitespace.
+    pub fn trimmed_offset(&self) -> usize {
+        self.offset
+    }
+
+    // Drops pending whitespace.
+    pub fn clear_whitespace(&mut self) {
+        self.whitespace.clear();
+    }
+
+    fn write(&mut self, string: &str) -> fmt::Result {
+        self.offset += string.len();
+        self.writer.write_str(string)
+    }
+}
+
+impl<W: Write> Write for Writer<'_, W> {
+    fn write_str(&mut self, mut string: &str) -> fmt::Result {
+        if !self.trim {
+            return self.write(string);
+        }
+
+        while !string.is_empty() {
+            // Buffer whitespace until any other character follows it.
+            let index = string
+                .find(|character| !is_whitespace(character))
+                .unwrap_or(string.len());
+            self.whitespace.push_str(&string[..index]);
+            string = &string[index..];
+
+            if string.is_empty() {
+                break;
+            } else if !string.starts_with(['\n', '\r']) {
+                self.offset += self.whitespace.len();
+                self.writer.write_str(&self.whitespace)?;
+            }
+
+            self.whitespace.clear();
+
+            let index = string.find(is_whitespace).unwrap_or(string.len());
+            self.write(&string[..index])?;
+            string = &string[index..];
+        }
+
+        Ok(())
+    }
+}
+
+fn is_whitespace(character: char) -> bool {
+    matches!(character, ' ' | '\t')
+}
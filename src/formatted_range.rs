@@ -0,0 +1,26 @@
+use alloc::string::String;
+use core::ops::Range;
+
+/// A formatted range of an output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormattedRange {
+    text: String,
+    range: Range<usize>,
+}
+
+impl FormattedRange {
+    /// Creates a formatted range.
+    pub const fn new(text: String, range: Range<usize>) -> Self {
+        Self { text, range }
+    }
+
+    /// Returns a formatted text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns a byte range of the text in a whole output.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
@@ -4,12 +4,22 @@
 extern crate alloc;
 
 mod build;
+mod column_alignment;
 mod document;
 mod format;
+mod formatted_range;
 mod options;
+#[cfg(feature = "parse")]
+pub mod parse;
+mod position;
+mod source_map;
 pub mod utility;
 
 pub use build::*;
+pub use column_alignment::*;
 pub use document::*;
 pub use format::*;
+pub use formatted_range::*;
 pub use options::*;
+pub use position::*;
+pub use source_map::*;
@@ -3,12 +3,27 @@
 pub struct FormatOptions {
     indent: usize,
     space: char,
+    max_width: usize,
+    width_unit: WidthUnit,
+    tab_width: usize,
+    smart_tab: bool,
+    line_ending: LineEnding,
+    trim_trailing_whitespace: bool,
 }
 
 impl FormatOptions {
     /// Creates options for indentation by spaces.
     pub const fn new(indent: usize) -> Self {
-        Self { indent, space: ' ' }
+        Self {
+            indent,
+            space: ' ',
+            max_width: usize::MAX,
+            width_unit: WidthUnit::Byte,
+            tab_width: 1,
+            smart_tab: false,
+            line_ending: LineEnding::Lf,
+            trim_trailing_whitespace: false,
+        }
     }
 
     /// Creates options for indentation by tabs.
@@ -16,6 +31,12 @@ impl FormatOptions {
         Self {
             indent: 1,
             space: '\t',
+            max_width: usize::MAX,
+            width_unit: WidthUnit::Byte,
+            tab_width: 1,
+            smart_tab: false,
+            line_ending: LineEnding::Lf,
+            trim_trailing_whitespace: false,
         }
     }
 
@@ -29,6 +50,36 @@ impl FormatOptions {
         self.space
     }
 
+    /// Returns a maximum line width.
+    pub const fn max_width(&self) -> usize {
+        self.max_width
+    }
+
+    /// Returns a unit of string widths.
+    pub const fn width_unit(&self) -> WidthUnit {
+        self.width_unit
+    }
+
+    /// Returns a tab width.
+    pub const fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Returns `true` if smart tabs are enabled.
+    pub const fn smart_tab(&self) -> bool {
+        self.smart_tab
+    }
+
+    /// Returns a line ending.
+    pub const fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Returns `true` if trailing whitespace is trimmed.
+    pub const fn trim_trailing_whitespace(&self) -> bool {
+        self.trim_trailing_whitespace
+    }
+
     /// Sets an indent size.
     pub const fn set_indent(self, indent: usize) -> Self {
         Self { indent, ..self }
@@ -38,4 +89,88 @@ impl FormatOptions {
     pub const fn set_space(self, space: char) -> Self {
         Self { space, ..self }
     }
+
+    /// Sets a maximum line width.
+    ///
+    /// Groups are broken into multiple lines only if they do not fit in it.
+    pub const fn set_max_width(self, max_width: usize) -> Self {
+        Self { max_width, ..self }
+    }
+
+    /// Sets a unit of string widths.
+    pub const fn set_width_unit(self, width_unit: WidthUnit) -> Self {
+        Self { width_unit, ..self }
+    }
+
+    /// Sets a tab width.
+    ///
+    /// A formatter uses it only to compute columns. It is 1 by default. Zero
+    /// is considered as 1.
+    pub const fn set_tab_width(self, tab_width: usize) -> Self {
+        Self { tab_width, ..self }
+    }
+
+    /// Enables or disables smart tabs.
+    ///
+    /// If they are enabled, a formatter renders indents by space characters
+    /// and alignment by [`Offside`](crate::Document::Offside) by spaces.
+    pub const fn set_smart_tab(self, smart_tab: bool) -> Self {
+        Self { smart_tab, ..self }
+    }
+
+    /// Sets a line ending.
+    pub const fn set_line_ending(self, line_ending: LineEnding) -> Self {
+        Self {
+            line_ending,
+            ..self
+        }
+    }
+
+    /// Enables or disables trimming of trailing spaces and tabs on every line.
+    pub const fn set_trim_trailing_whitespace(self, trim_trailing_whitespace: bool) -> Self {
+        Self {
+            trim_trailing_whitespace,
+            ..self
+        }
+    }
+}
+
+/// A unit of string widths.
+///
+/// A formatter uses it to compute columns and line widths.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WidthUnit {
+    /// UTF-8 bytes.
+    #[default]
+    Byte,
+    /// Unicode scalar values.
+    Char,
+    /// Display columns in terminals and editors.
+    ///
+    /// East Asian wide characters take two columns and combining characters
+    /// take no column.
+    Display,
+}
+
+/// A line ending.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    /// A line feed.
+    #[default]
+    Lf,
+    /// A carriage return followed by a line feed.
+    CrLf,
+    /// A carriage return.
+    Cr,
+}
+
+impl LineEnding {
+    /// Returns a string of a line ending.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
 }
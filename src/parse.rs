@@ -0,0 +1,693 @@
+//! A parser of documents in a textual syntax.
+//!
+//! The syntax is the one of [`dump`](crate::utility::dump) like
+//! `break(["{", indent([line, "foo"]), line, "}"])`.
+
+mod frame;
+
+use crate::{
+    Builder, ColumnAlignment, Document, cursor, group, hard_line, line, line_suffix,
+    line_suffix_boundary, mark, prefix, soft_line, verbatim,
+};
+use alloc::{string::String, vec, vec::Vec};
+use allocator_api2::alloc::Allocator;
+use core::{error::Error, fmt, mem::take};
+use frame::Frame;
+
+/// A parse error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    offset: usize,
+    message: &'static str,
+}
+
+impl ParseError {
+    /// Returns a byte offset in a source.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns a message.
+    pub const fn message(&self) -> &'static str {
+        self.message
+    }
+}
+
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+/// Parses a document.
+pub fn parse<'a, A: Allocator + Clone + 'a>(
+    builder: &Builder<A>,
+    source: &str,
+) -> Result<Document<'a>, ParseError> {
+    let mut parser = Parser {
+        builder,
+        source,
+        offset: 0,
+    };
+    let document = parser.document()?;
+
+    parser.skip_whitespace();
+
+    if parser.offset < source.len() {
+        return Err(parser.error("unexpected character"));
+    }
+
+    Ok(document)
+}
+
+struct Parser<'b, 's, A: Allocator> {
+    builder: &'b Builder<A>,
+    source: &'s str,
+    offset: usize,
+}
+
+impl<'a, 's, A: Allocator + Clone + 'a> Parser<'_, 's, A> {
+    // Parses a document with an explicit stack of frames so that deeply nested
+    // documents do not overflow a call stack.
+    fn document(&mut self) -> Result<Document<'a>, ParseError> {
+        let mut frames = vec![];
+        let mut document = None;
+
+        loop {
+            if let Some(frame) = frames.last_mut() {
+                document = self.resume(frame, document)?;
+
+                if document.is_some() {
+                    frames.pop();
+                    continue;
+                }
+            } else if let Some(document) = document {
+                return Ok(document);
+            }
+
+            document = self.start(&mut frames)?;
+        }
+    }
+
+    // Parses a leaf document or pushes a frame of a composite document.
+    fn start(&mut self, frames: &mut Vec<Frame<'a>>) -> Result<Option<Document<'a>>, ParseError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('"') {
+            return Ok(Some(self.string()?.into()));
+        } else if self.rest().starts_with('[') {
+            self.offset += 1;
+            frames.push(Frame::Sequence(vec![]));
+
+            return Ok(None);
+        }
+
+        let offset = self.offset;
+        let identifier = self.identifier()?;
+
+        if let Some(document) = self.leaf(identifier)? {
+            return Ok(Some(document));
+        }
+
+        frames.push(match identifier {
+            "align" => {
+                self.expect('(')?;
+                let alignment = self.number()?;
+                self.expect(',')?;
+
+                Frame::Align(alignment)
+            }
+            "aligned_line_suffixes" => self.open(Frame::AlignedLineSuffixes)?,
+            "break" => self.open(Frame::Break)?,
+            "cache" => self.open(Frame::Cache)?,
+            "dedent" => self.open(Frame::Dedent)?,
+            "dedent_to_root" => self.open(Frame::DedentToRoot)?,
+            "fill" => {
+                let frame = self.open(Frame::Fill(vec![]))?;
+                self.expect('[')?;
+
+                frame
+            }
+            "flatten" => self.open(Frame::Flatten)?,
+            "group" => self.open(Frame::Group)?,
+            "if_break" => self.open(Frame::IfBreak(None))?,
+            "indent" => self.open(Frame::Indent)?,
+            "offside" => self.open(Frame::Offside)?,
+            "prefix" => {
+                self.expect('(')?;
+                let string = self.string()?;
+                self.expect(',')?;
+
+                Frame::Prefix(string)
+            }
+            "table" => {
+                let frame = self.open(Frame::Table {
+                    rows: vec![],
+                    row: None,
+                })?;
+                self.expect('[')?;
+
+                frame
+            }
+            _ => {
+                return Err(ParseError {
+                    offset,
+                    message: "unknown document",
+                });
+            }
+        });
+
+        Ok(None)
+    }
+
+    fn leaf(&mut self, identifier: &str) -> Result<Option<Document<'a>>, ParseError> {
+        Ok(Some(match identifier {
+            "cursor" => cursor(),
+            "hard_line" => hard_line(),
+            "line" => line(),
+            "line_suffix" => {
+                self.expect('(')?;
+                let string = self.string()?;
+                self.close()?;
+
+                line_suffix(string)
+            }
+            "line_suffix_boundary" => line_suffix_boundary(),
+            "mark" => {
+                self.expect('(')?;
+                let id = self.number()?;
+                self.close()?;
+
+                mark(id)
+            }
+            "soft_line" => soft_line(),
+            "verbatim" => {
+                self.expect('(')?;
+                let string = self.string()?;
+                self.expect(',')?;
+                let indent = self.boolean()?;
+                self.close()?;
+
+                verbatim(string, indent)
+            }
+            _ => return Ok(None),
+        }))
+    }
+
+    fn open(&mut self, frame: Frame<'a>) -> Result<Frame<'a>, ParseError> {
+        self.expect('(')?;
+
+        Ok(frame)
+    }
+
+    // Parses the rest of a frame after a child document if any.
+    //
+    // It returns a document if the frame ends or `None` if another child
+    // document follows.
+    fn resume(
+        &mut self,
+        frame: &mut Frame<'a>,
+        document: Option<Document<'a>>,
+    ) -> Result<Option<Document<'a>>, ParseError> {
+        let builder = self.builder;
+
+        let document = match (frame, document) {
+            (Frame::Fill(documents), document) => {
+                documents.extend(document);
+
+                if self.element(']', documents.is_empty())? {
+                    return Ok(None);
+                }
+
+                builder.fill(take(documents))
+            }
+            (Frame::Sequence(documents), document) => {
+                documents.extend(document);
+
+                return Ok((!self.element(']', documents.is_empty())?)
+                    .then(|| builder.sequence(take(documents))));
+            }
+            (Frame::Table { rows, row }, document) => {
+                if let Some(documents) = row {
+                    documents.extend(document);
+                }
+
+                loop {
+                    if let Some(documents) = row {
+                        if self.element(']', documents.is_empty())? {
+                            return Ok(None);
+                        }
+
+                        rows.extend(row.take());
+                    } else if self.element(']', rows.is_empty())? {
+                        self.expect('[')?;
+                        *row = Some(vec![]);
+                    } else {
+                        self.expect(',')?;
+                        let alignments = self.list('[', ']', Self::column_alignment)?;
+
+                        break builder.table(take(rows), alignments);
+                    }
+                }
+            }
+            (_, None) => return Ok(None),
+            (Frame::Align(alignment), Some(document)) => builder.align(*alignment, document),
+            (Frame::AlignedLineSuffixes, Some(document)) => builder.aligned_line_suffixes(document),
+            (Frame::Break, Some(document)) => builder.r#break(document),
+            (Frame::Cache, Some(document)) => builder.cache(document),
+            (Frame::Dedent, Some(document)) => builder.dedent(document),
+            (Frame::DedentToRoot, Some(document)) => {
+                self.expect(',')?;
+                let offside = self.boolean()?;
+
+                builder.dedent_to_root(document, offside)
+            }
+            (Frame::Flatten, Some(document)) => builder.flatten(document),
+            (Frame::Group, Some(document)) => group(builder.allocate(document)),
+            (Frame::IfBreak(broken), Some(document)) => {
+                let Some(broken) = broken.take() else {
+                    *broken = Some(document);
+                    self.expect(',')?;
+
+                    return Ok(None);
+                };
+
+                builder.if_break(broken, document)
+            }
+            (Frame::Indent, Some(document)) => builder.indent(document),
+            (Frame::Offside, Some(document)) => {
+                self.expect(',')?;
+                let soft = self.boolean()?;
+
+                builder.offside(document, soft)
+            }
+            (Frame::Prefix(string), Some(document)) => prefix(string, builder.allocate(document)),
+        };
+
+        self.close()?;
+
+        Ok(Some(document))
+    }
+
+    // Parses an optional trailing comma and a closing parenthesis.
+    fn close(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(',') {
+            self.offset += 1;
+        }
+
+        self.expect(')')
+    }
+
+    fn list<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut element: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut elements = Vec::new();
+
+        self.expect(open)?;
+
+        while self.element(close, elements.is_empty())? {
+            elements.push(element(self)?);
+        }
+
+        Ok(elements)
+    }
+
+    // Parses a separator or a closing bracket after list elements and checks
+    // if another element follows.
+    fn element(&mut self, close: char, first: bool) -> Result<bool, ParseError> {
+        self.skip_whitespace();
+
+        if !first {
+            if self.rest().starts_with(',') {
+                self.offset += 1;
+                self.skip_whitespace();
+            } else {
+                self.expect(close)?;
+                return Ok(false);
+            }
+        }
+
+        if self.rest().starts_with(close) {
+            self.offset += close.len_utf8();
+            Ok(false)
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'s str, ParseError> {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+        let length = rest
+            .find(|character: char| !character.is_ascii_alphanumeric() && character != '_')
+            .unwrap_or(rest.len());
+
+        if length == 0 {
+            return Err(self.error("document expected"));
+        }
+
+        self.offset += length;
+
+        Ok(&rest[..length])
+    }
+
+    fn boolean(&mut self) -> Result<bool, ParseError> {
+        let offset = self.offset;
+
+        match self.identifier() {
+            Ok("false") => Ok(false),
+            Ok("true") => Ok(true),
+            _ => {
+                self.offset = offset;
+                self.skip_whitespace();
+                Err(self.error("boolean expected"))
+            }
+        }
+    }
+
+    fn column_alignment(&mut self) -> Result<ColumnAlignment, ParseError> {
+        let offset = self.offset;
+
+        match self.identifier() {
+            Ok("left") => Ok(ColumnAlignment::Left),
+            Ok("right") => Ok(ColumnAlignment::Right),
+            _ => {
+                self.offset = offset;
+                self.skip_whitespace();
+                Err(self.error("column alignment expected"))
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, ParseError> {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+        let length = rest
+            .find(|character: char| !character.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..length]
+            .parse()
+            .map_err(|_| self.error("number expected"))?;
+
+        self.offset += length;
+
+        Ok(number)
+    }
+
+    fn string(&mut self) -> Result<&'a str, ParseError> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+
+        loop {
+            let rest = self.rest();
+            let mut characters = rest.chars();
+
+            match characters.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.offset += 1;
+                    break;
+                }
+                Some('\\') => {
+                    let (character, length) = match characters.next() {
+                        Some('"') => ('"', 2),
+                        Some('\'') => ('\'', 2),
+                        Some('\\') => ('\\', 2),
+                        Some('0') => ('\0', 2),
+                        Some('n') => ('\n', 2),
+                        Some('r') => ('\r', 2),
+                        Some('t') => ('\t', 2),
+                        Some('u') => {
+                            let (code, _) = characters
+                                .as_str()
+                                .strip_prefix('{')
+                                .and_then(|rest| rest.split_once('}'))
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            let character = u32::from_str_radix(code, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+
+                            (character, code.len() + 4)
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    self.offset += length;
+                    string.push(character);
+                }
+                Some(character) => {
+                    self.offset += character.len_utf8();
+                    string.push(character);
+                }
+            }
+        }
+
+        Ok(self.builder.allocate_str([string.as_str()]))
+    }
+
+    fn expect(&mut self, character: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(character) {
+            self.offset += character.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(match character {
+                '(' => "'(' expected",
+                ')' => "')' expected",
+                ',' => "',' expected",
+                '[' => "'[' expected",
+                ']' => "']' expected",
+                _ => "'\"' expected",
+            }))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.offset += self.rest().len() - self.rest().trim_start().len();
+    }
+
+    fn rest(&self) -> &'s str {
+        &self.source[self.offset..]
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            offset: self.offset,
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::{count_lines, is_broken};
+    use crate::{
+        FormatOptions, align, aligned_line_suffixes, r#break, cache, dedent, dedent_to_root, fill,
+        flatten, format, group, if_break, indent, offside, sequence, table, utility::dump,
+    };
+    use alloc::{format, string::ToString};
+    use bumpalo::Bump;
+    use pretty_assertions::assert_eq;
+
+    fn dump_to_string(document: &Document) -> String {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut string = String::new();
+
+        format(
+            &dump(&builder, document),
+            &mut string,
+            FormatOptions::new(2),
+        )
+        .unwrap();
+
+        string
+    }
+
+    #[test]
+    fn parse_leaves() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        for (source, document) in [
+            (r#""foo""#, "foo".into()),
+            ("line", line()),
+            ("soft_line", soft_line()),
+            ("hard_line", hard_line()),
+            ("cursor", cursor()),
+            ("mark(42)", mark(42)),
+            (r#"line_suffix("// foo")"#, line_suffix("// foo")),
+            ("line_suffix_boundary", line_suffix_boundary()),
+        ] {
+            assert_eq!(parse(&builder, source), Ok(document));
+        }
+    }
+
+    #[test]
+    fn parse_nested_documents() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        assert_eq!(
+            parse(
+                &builder,
+                r#"break(["{", indent([line, "foo"]), line, "}"])"#
+            ),
+            Ok(r#break(&sequence(&[
+                "{".into(),
+                indent(&sequence(&[line(), "foo".into()])),
+                line(),
+                "}".into(),
+            ])))
+        );
+        assert_eq!(parse(&builder, "cache(hard_line)"), Ok(cache(&hard_line())));
+    }
+
+    #[test]
+    fn parse_whitespace_and_trailing_commas() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        assert_eq!(
+            parse(&builder, " group(\n  [\n    \"foo\",\n    line,\n  ],\n)\n"),
+            Ok(group(&sequence(&["foo".into(), line()])))
+        );
+    }
+
+    #[test]
+    fn parse_escapes() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        assert_eq!(
+            parse(&builder, r#""\"\\\n\r\t\0\'\u{1f600}""#),
+            Ok("\"\\\n\r\t\0'\u{1f600}".into())
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        for (source, offset, message) in [
+            ("", 0, "document expected"),
+            ("foo", 0, "unknown document"),
+            ("group", 5, "'(' expected"),
+            ("group(line", 10, "')' expected"),
+            ("[line line]", 6, "']' expected"),
+            ("if_break(line line)", 14, "',' expected"),
+            ("offside(line, maybe)", 14, "boolean expected"),
+            ("mark(x)", 5, "number expected"),
+            ("table([], [center])", 11, "column alignment expected"),
+            (r#""foo"#, 4, "unterminated string"),
+            (r#""\x""#, 1, "invalid escape"),
+            (r#""\u{110000}""#, 1, "invalid unicode escape"),
+            ("line line", 5, "unexpected character"),
+        ] {
+            assert_eq!(
+                parse(&builder, source),
+                Err(ParseError { offset, message }),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_deep_documents() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let source = format!("{}line{}", "indent([".repeat(100_000), "])".repeat(100_000));
+        let document = parse(&builder, &source).unwrap();
+
+        assert!(!is_broken(&document));
+        assert_eq!(count_lines(&document), 1);
+        assert_eq!(
+            parse(&builder, &"indent(".repeat(100_000)),
+            Err(ParseError {
+                offset: 700_000,
+                message: "document expected"
+            })
+        );
+    }
+
+    #[test]
+    fn parse_table_errors() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        for (source, offset, message) in [
+            ("table([line], [])", 7, "'[' expected"),
+            ("table([[line line]], [])", 13, "']' expected"),
+            ("table([[] []], [])", 10, "']' expected"),
+            ("table([[]])", 10, "',' expected"),
+        ] {
+            assert_eq!(
+                parse(&builder, source),
+                Err(ParseError { offset, message }),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn display_error() {
+        assert_eq!(
+            ParseError {
+                offset: 42,
+                message: "unknown document"
+            }
+            .to_string(),
+            "unknown document at offset 42"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        for document in [
+            sequence(&[]),
+            "\"foo\tbar\u{7f}\"".into(),
+            r#break(&sequence(&["{".into(), indent(&line()), "}".into()])),
+            flatten(&"foo".into()),
+            group(&sequence(&["foo".into(), line(), "bar".into()])),
+            if_break(&",".into(), &"".into()),
+            offside(&sequence(&[mark(0), cursor()]), true),
+            offside(&line_suffix("// foo"), false),
+            sequence(&[line_suffix_boundary(), soft_line()]),
+            fill(&[]),
+            align(3, &sequence(&["foo".into(), hard_line()])),
+            dedent(&"foo".into()),
+            verbatim("foo\nbar", true),
+            prefix("// ", &sequence(&["foo".into(), hard_line()])),
+            table(&[], &[]),
+            aligned_line_suffixes(&sequence(&["foo".into(), line_suffix(" // foo")])),
+            table(
+                &[&["foo".into(), line()], &[], &["bar".into()]],
+                &[ColumnAlignment::Left, ColumnAlignment::Right],
+            ),
+            dedent_to_root(&"foo".into(), true),
+            fill(&["foo".into(), line(), "bar".into()]),
+        ] {
+            let string = dump_to_string(&document);
+
+            assert_eq!(parse(&builder, &string), Ok(document.clone()));
+            assert_eq!(dump_to_string(&parse(&builder, &string).unwrap()), string);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::Document;
+use alloc::vec::Vec;
+
+// A composite document being parsed.
+#[derive(Debug)]
+pub enum Frame<'a> {
+    Align(usize),
+    AlignedLineSuffixes,
+    Break,
+    Cache,
+    Dedent,
+    DedentToRoot,
+    Fill(Vec<Document<'a>>),
+    Flatten,
+    Group,
+    // A broken document parsed already.
+    IfBreak(Option<Document<'a>>),
+    Indent,
+    Offside,
+    Prefix(&'a str),
+    Sequence(Vec<Document<'a>>),
+    // Rows parsed already and a row being parsed.
+    Table {
+        rows: Vec<Vec<Document<'a>>>,
+        row: Option<Vec<Document<'a>>>,
+    },
+}
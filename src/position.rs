@@ -0,0 +1,34 @@
+/// A position in a formatted output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    /// Creates a position.
+    pub const fn new(offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    /// Returns a byte offset.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns a zero-based line number.
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns a zero-based column in a unit of
+    /// [`WidthUnit`](crate::WidthUnit).
+    pub const fn column(&self) -> usize {
+        self.column
+    }
+}
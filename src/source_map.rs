@@ -0,0 +1,25 @@
+use crate::Position;
+
+/// A mapping from a mark in a document to a position in a formatted output.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Mapping {
+    id: usize,
+    position: Position,
+}
+
+impl Mapping {
+    /// Creates a mapping.
+    pub const fn new(id: usize, position: Position) -> Self {
+        Self { id, position }
+    }
+
+    /// Returns a mark ID.
+    pub const fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns a position.
+    pub const fn position(&self) -> Position {
+        self.position
+    }
+}
@@ -1,62 +1,206 @@
 //! Utilities.
 
+mod dump;
+mod edit;
+
 use super::Document;
+use alloc::vec;
+pub use dump::*;
+pub use edit::*;
 
 /// Checks if a document is broken into multiple lines.
 pub fn is_broken(document: &Document) -> bool {
-    match document {
-        Document::Break { broken, document } => *broken || is_broken(document),
-        Document::Indent(document) | Document::Offside { document, .. } => is_broken(document),
-        Document::Sequence(documents) => documents.iter().any(is_broken),
-        Document::Line | Document::LineSuffix(_) | Document::String(_) => false,
+    let mut documents = vec![document];
+
+    while let Some(document) = documents.pop() {
+        match document {
+            Document::Break { broken, document } => {
+                if *broken {
+                    return true;
+                }
+
+                documents.push(document);
+            }
+            Document::Align(_, document)
+            | Document::AlignedLineSuffixes(document)
+            | Document::Dedent(document)
+            | Document::DedentToRoot { document, .. }
+            | Document::Group(document)
+            | Document::Indent(document)
+            | Document::Offside { document, .. }
+            | Document::Prefix(_, document) => documents.push(document),
+            Document::Cache { broken, .. } => {
+                if *broken {
+                    return true;
+                }
+            }
+            Document::HardLine => return true,
+            Document::Table { rows, .. } => {
+                if rows.len() > 1 {
+                    return true;
+                }
+
+                documents.extend(rows.iter().flat_map(|row| row.iter()));
+            }
+            Document::Verbatim { string, .. } => {
+                if string.contains('\n') {
+                    return true;
+                }
+            }
+            Document::IfBreak { flat, .. } => documents.push(flat),
+            Document::Fill(sequence) | Document::Sequence(sequence) => documents.extend(*sequence),
+            Document::Cursor
+            | Document::Line
+            | Document::LineSuffix(_)
+            | Document::LineSuffixBoundary
+            | Document::Mark(_)
+            | Document::SoftLine
+            | Document::String(_) => {}
+        }
     }
+
+    false
 }
 
 /// Counts lines in a document.
 pub fn count_lines(document: &Document) -> usize {
-    match document {
-        Document::Break { broken, document } => {
-            if *broken {
-                count_lines(document)
-            } else {
-                0
+    let mut documents = vec![(document, true)];
+    let mut count = 0;
+
+    while let Some((document, broken)) = documents.pop() {
+        match document {
+            // Flat documents can still contain forced lines.
+            Document::Break { broken, document } => documents.push((document, *broken)),
+            Document::Group(document) => documents.push((document, is_broken(document))),
+            Document::IfBreak {
+                broken: document,
+                flat,
+            } => documents.push((if broken { document } else { flat }, broken)),
+            Document::Align(_, document)
+            | Document::AlignedLineSuffixes(document)
+            | Document::Cache { document, .. }
+            | Document::Dedent(document)
+            | Document::DedentToRoot { document, .. }
+            | Document::Indent(document)
+            | Document::Offside { document, .. }
+            | Document::Prefix(_, document) => documents.push((document, broken)),
+            Document::HardLine => count += 1,
+            Document::Line | Document::SoftLine => count += usize::from(broken),
+            Document::Table { rows, .. } => {
+                count += rows.len().saturating_sub(1);
+
+                // Cells are rendered flat until the first broken one in each row.
+                for row in rows.iter() {
+                    let mut aligned = true;
+
+                    for cell in row.iter() {
+                        aligned &= !is_broken(cell);
+                        documents.push((cell, broken && !aligned));
+                    }
+                }
             }
+            Document::Verbatim { string, .. } => count += string.matches('\n').count(),
+            Document::Fill(sequence) | Document::Sequence(sequence) => {
+                documents.extend(sequence.iter().map(|document| (document, broken)))
+            }
+            Document::Cursor
+            | Document::LineSuffix(_)
+            | Document::LineSuffixBoundary
+            | Document::Mark(_)
+            | Document::String(_) => {}
         }
-        Document::Indent(document) | Document::Offside { document, .. } => count_lines(document),
-        Document::Line => 1,
-        Document::Sequence(documents) => documents.iter().map(count_lines).sum(),
-        Document::LineSuffix(_) | Document::String(_) => 0,
     }
+
+    count
 }
 
 /// Checks if a document is empty.
 pub fn is_empty(document: &Document) -> bool {
-    match document {
-        Document::Break { document, .. }
-        | Document::Indent(document)
-        | Document::Offside { document, .. } => is_empty(document),
-        Document::Sequence(documents) => documents.iter().all(is_empty),
-        Document::LineSuffix(string) | Document::String(string) => string.is_empty(),
-        Document::Line => false,
+    let mut documents = vec![document];
+
+    while let Some(document) = documents.pop() {
+        match document {
+            Document::Align(_, document)
+            | Document::AlignedLineSuffixes(document)
+            | Document::Break { document, .. }
+            | Document::Cache { document, .. }
+            | Document::Dedent(document)
+            | Document::DedentToRoot { document, .. }
+            | Document::Group(document)
+            | Document::Indent(document)
+            | Document::Offside { document, .. }
+            | Document::Prefix(_, document) => documents.push(document),
+            Document::IfBreak { broken, flat } => documents.extend([broken, flat]),
+            Document::Fill(sequence) | Document::Sequence(sequence) => documents.extend(*sequence),
+            Document::LineSuffix(string)
+            | Document::String(string)
+            | Document::Verbatim { string, .. } => {
+                if !string.is_empty() {
+                    return false;
+                }
+            }
+            Document::Cursor | Document::LineSuffixBoundary | Document::Mark(_) => {}
+            Document::HardLine | Document::Line | Document::SoftLine => return false,
+            Document::Table { rows, .. } => {
+                if rows.len() > 1 {
+                    return false;
+                }
+
+                documents.extend(rows.iter().flat_map(|row| row.iter()));
+            }
+        }
     }
+
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{r#break, flatten, indent, line, line_suffix, sequence};
+    use crate::{
+        Builder, align, aligned_line_suffixes, r#break, cache, cursor, fill, flatten, flatten_if,
+        group, hard_line, if_break, indent, line, line_suffix, line_suffix_boundary, mark, prefix,
+        sequence, soft_line, table, verbatim,
+    };
+    use bumpalo::Bump;
 
     #[test]
     fn check_empty() {
         assert!(is_empty(&"".into()));
         assert!(!is_empty(&"foo".into()));
         assert!(!is_empty(&line()));
+        assert!(!is_empty(&soft_line()));
+        assert!(!is_empty(&hard_line()));
         assert!(is_empty(&line_suffix("")));
         assert!(!is_empty(&line_suffix("foo")));
+        assert!(is_empty(&line_suffix_boundary()));
+        assert!(is_empty(&mark(0)));
+        assert!(is_empty(&cursor()));
         assert!(is_empty(&indent(&"".into())));
         assert!(!is_empty(&indent(&"foo".into())));
         assert!(is_empty(&r#break(&"".into())));
         assert!(!is_empty(&r#break(&"foo".into())));
+        assert!(is_empty(&group(&"".into())));
+        assert!(!is_empty(&group(&"foo".into())));
+        assert!(is_empty(&if_break(&"".into(), &"".into())));
+        assert!(!is_empty(&if_break(&",".into(), &"".into())));
+        assert!(!is_empty(&if_break(&"".into(), &",".into())));
+        assert!(is_empty(&cache(&"".into())));
+        assert!(!is_empty(&cache(&"foo".into())));
+        assert!(is_empty(&fill(&[])));
+        assert!(is_empty(&verbatim("", false)));
+        assert!(is_empty(&table(&[], &[])));
+        assert!(is_empty(&aligned_line_suffixes(&"".into())));
+        assert!(!is_empty(&aligned_line_suffixes(&"foo".into())));
+        assert!(is_empty(&table(&[&["".into()]], &[])));
+        assert!(!is_empty(&table(&[&["foo".into()]], &[])));
+        assert!(!is_empty(&table(&[&[], &[]], &[])));
+        assert!(is_empty(&prefix("// ", &"".into())));
+        assert!(!is_empty(&prefix("// ", &"foo".into())));
+        assert!(!is_empty(&verbatim("foo", false)));
+        assert!(is_empty(&align(3, &"".into())));
+        assert!(!is_empty(&align(3, &"foo".into())));
+        assert!(!is_empty(&fill(&["foo".into()])));
     }
 
     #[test]
@@ -64,12 +208,95 @@ mod tests {
         assert!(!is_broken(&"".into()));
         assert!(!is_broken(&"foo".into()));
         assert!(!is_broken(&line()));
+        assert!(!is_broken(&soft_line()));
+        assert!(is_broken(&hard_line()));
+        assert!(is_broken(&flatten(&sequence(&["foo".into(), hard_line()]))));
+        assert!(is_broken(&flatten_if(true, &hard_line())));
         assert!(!is_broken(&line_suffix("foo")));
+        assert!(!is_broken(&line_suffix_boundary()));
         assert!(!is_broken(&indent(&"foo".into())));
         assert!(!is_broken(&flatten(&"".into())));
         assert!(is_broken(&r#break(&"".into())));
         assert!(is_broken(&r#break(&flatten(&"".into()))));
         assert!(is_broken(&flatten(&r#break(&"".into()))));
         assert!(is_broken(&flatten(&sequence(&[r#break(&"".into())]))));
+        assert!(!is_broken(&group(&line())));
+        assert!(is_broken(&group(&r#break(&"".into()))));
+        assert!(!is_broken(&if_break(&r#break(&"".into()), &"".into())));
+        assert!(is_broken(&if_break(&"".into(), &r#break(&"".into()))));
+        assert!(!is_broken(&cache(&"".into())));
+        assert!(is_broken(&cache(&hard_line())));
+        assert!(!is_broken(&fill(&["foo".into(), line()])));
+        assert!(is_broken(&fill(&["foo".into(), hard_line()])));
+        assert!(!is_broken(&verbatim("foo", true)));
+        assert!(is_broken(&verbatim("foo\nbar", true)));
+        assert!(!is_broken(&table(&[&["foo".into()]], &[])));
+        assert!(is_broken(&table(&[&["foo".into()], &["bar".into()]], &[])));
+        assert!(!is_broken(&Document::Cache {
+            broken: false,
+            document: &hard_line()
+        }));
+    }
+
+    #[test]
+    fn count_soft_lines() {
+        assert_eq!(count_lines(&soft_line()), 1);
+        assert_eq!(count_lines(&sequence(&[line(), soft_line()])), 2);
+        assert_eq!(count_lines(&flatten(&soft_line())), 0);
+        assert_eq!(count_lines(&fill(&["foo".into(), line()])), 1);
+    }
+
+    #[test]
+    fn count_hard_lines() {
+        assert_eq!(count_lines(&hard_line()), 1);
+        assert_eq!(count_lines(&r#break(&hard_line())), 1);
+        assert_eq!(count_lines(&verbatim("foo\nbar\n", false)), 2);
+        assert_eq!(
+            count_lines(&flatten(&sequence(&["a".into(), hard_line(), "b".into()]))),
+            1
+        );
+        assert_eq!(count_lines(&flatten(&verbatim("foo\nbar", false))), 1);
+        assert_eq!(
+            count_lines(&flatten(&table(&[&["foo".into()], &["bar".into()]], &[]))),
+            1
+        );
+        assert_eq!(count_lines(&table(&[&[line()], &[], &[]], &[])), 2);
+        assert_eq!(count_lines(&table(&[&[hard_line()], &[]], &[])), 2);
+        assert_eq!(count_lines(&table(&[&[r#break(&line()), line()]], &[])), 2);
+    }
+
+    #[test]
+    fn count_group_lines() {
+        assert_eq!(count_lines(&group(&line())), 0);
+        assert_eq!(count_lines(&group(&r#break(&line()))), 1);
+        assert_eq!(
+            count_lines(&group(&sequence(&[line(), verbatim("foo\nbar", false)]))),
+            2
+        );
+    }
+
+    #[test]
+    fn count_if_break_lines() {
+        assert_eq!(count_lines(&if_break(&line(), &"".into())), 1);
+        assert_eq!(count_lines(&if_break(&"".into(), &line())), 0);
+    }
+
+    #[test]
+    fn check_deep_document() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut document = line();
+
+        for index in 0..1_000_000 {
+            document = match index % 3 {
+                0 => builder.sequence([document, "".into()]),
+                1 => builder.indent(document),
+                _ => builder.r#break(document),
+            };
+        }
+
+        assert!(is_broken(&document));
+        assert!(!is_empty(&document));
+        assert_eq!(count_lines(&document), 1);
     }
 }
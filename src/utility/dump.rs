@@ -0,0 +1,354 @@
+use crate::{Builder, ColumnAlignment, Document, if_break, line, soft_line, utility::is_broken};
+use alloc::{format, vec, vec::Vec};
+use allocator_api2::alloc::Allocator;
+
+/// Dumps a document as an expression of document builders.
+///
+/// It creates a document of an expression like `group(indent([line, "foo"]))`
+/// which is broken into multiple lines by [`format`](crate::format) only if it
+/// does not fit in a maximum width.
+pub fn dump<'a, A: Allocator + Clone + 'a>(
+    builder: &Builder<A>,
+    document: &Document,
+) -> Document<'a> {
+    let mut tasks = vec![(document, false)];
+    let mut results = vec![];
+
+    while let Some((document, built)) = tasks.pop() {
+        if built {
+            let arguments = results.split_off(results.len() - children(document).len());
+            results.push(build(builder, document, arguments));
+        } else {
+            tasks.push((document, true));
+            tasks.extend(
+                children(document)
+                    .into_iter()
+                    .rev()
+                    .map(|document| (document, false)),
+            );
+        }
+    }
+
+    results.pop().unwrap_or_else(|| "".into())
+}
+
+fn children<'a>(document: &'a Document<'a>) -> Vec<&'a Document<'a>> {
+    match document {
+        Document::Align(_, document)
+        | Document::AlignedLineSuffixes(document)
+        | Document::Break { document, .. }
+        | Document::Cache { document, .. }
+        | Document::Dedent(document)
+        | Document::DedentToRoot { document, .. }
+        | Document::Group(document)
+        | Document::Indent(document)
+        | Document::Offside { document, .. }
+        | Document::Prefix(_, document) => vec![document],
+        Document::IfBreak { broken, flat } => vec![broken, flat],
+        Document::Table { rows, .. } => rows.iter().flat_map(|row| row.iter()).collect(),
+        Document::Fill(documents) | Document::Sequence(documents) => documents.iter().collect(),
+        Document::Cursor
+        | Document::HardLine
+        | Document::Line
+        | Document::LineSuffix(_)
+        | Document::LineSuffixBoundary
+        | Document::Mark(_)
+        | Document::SoftLine
+        | Document::String(_)
+        | Document::Verbatim { .. } => vec![],
+    }
+}
+
+fn build<'a, A: Allocator + Clone + 'a>(
+    builder: &Builder<A>,
+    document: &Document,
+    mut arguments: Vec<Document<'a>>,
+) -> Document<'a> {
+    match document {
+        Document::Align(alignment, _) => {
+            arguments.insert(0, builder.strings([format!("{alignment}").as_str()]));
+            call(builder, "align", arguments)
+        }
+        Document::AlignedLineSuffixes(_) => call(builder, "aligned_line_suffixes", arguments),
+        Document::Break { broken, .. } => call(
+            builder,
+            if *broken { "break" } else { "flatten" },
+            arguments,
+        ),
+        // Omit caches consistent with their documents as builders create them
+        // implicitly.
+        Document::Cache { broken, document } if *broken == is_broken(document) => {
+            arguments.pop().unwrap_or_else(|| "".into())
+        }
+        Document::Cache { .. } => call(builder, "cache", arguments),
+        Document::Cursor => "cursor".into(),
+        Document::Dedent(_) => call(builder, "dedent", arguments),
+        Document::DedentToRoot { offside, .. } => {
+            arguments.push(if *offside { "true" } else { "false" }.into());
+            call(builder, "dedent_to_root", arguments)
+        }
+        Document::Fill(_) => call(builder, "fill", vec![list(builder, "[", "]", arguments)]),
+        Document::Group(_) => call(builder, "group", arguments),
+        Document::HardLine => "hard_line".into(),
+        Document::IfBreak { .. } => call(builder, "if_break", arguments),
+        Document::Indent(_) => call(builder, "indent", arguments),
+        Document::Line => "line".into(),
+        Document::LineSuffix(string) => call(builder, "line_suffix", vec![quote(builder, string)]),
+        Document::LineSuffixBoundary => "line_suffix_boundary".into(),
+        Document::Mark(id) => call(
+            builder,
+            "mark",
+            vec![builder.strings([format!("{id}").as_str()])],
+        ),
+        Document::Offside { soft, .. } => {
+            arguments.push(if *soft { "true" } else { "false" }.into());
+            call(builder, "offside", arguments)
+        }
+        Document::Prefix(prefix, _) => {
+            arguments.insert(0, quote(builder, prefix));
+            call(builder, "prefix", arguments)
+        }
+        Document::Sequence(_) => list(builder, "[", "]", arguments),
+        Document::SoftLine => "soft_line".into(),
+        Document::String(string) => quote(builder, string),
+        Document::Table { rows, alignments } => {
+            let mut cells = arguments.into_iter();
+
+            call(
+                builder,
+                "table",
+                vec![
+                    list(
+                        builder,
+                        "[",
+                        "]",
+                        rows.iter()
+                            .map(|row| {
+                                list(builder, "[", "]", cells.by_ref().take(row.len()).collect())
+                            })
+                            .collect(),
+                    ),
+                    list(
+                        builder,
+                        "[",
+                        "]",
+                        alignments
+                            .iter()
+                            .map(|alignment| match alignment {
+                                ColumnAlignment::Left => "left".into(),
+                                ColumnAlignment::Right => "right".into(),
+                            })
+                            .collect(),
+                    ),
+                ],
+            )
+        }
+        Document::Verbatim { string, indent } => call(
+            builder,
+            "verbatim",
+            vec![
+                quote(builder, string),
+                if *indent { "true" } else { "false" }.into(),
+            ],
+        ),
+    }
+}
+
+fn call<'a, A: Allocator + Clone + 'a>(
+    builder: &Builder<A>,
+    name: &'static str,
+    arguments: Vec<Document<'a>>,
+) -> Document<'a> {
+    builder.sequence([name.into(), list(builder, "(", ")", arguments)])
+}
+
+fn list<'a, A: Allocator + Clone + 'a>(
+    builder: &Builder<A>,
+    open: &'static str,
+    close: &'static str,
+    elements: Vec<Document<'a>>,
+) -> Document<'a> {
+    if elements.is_empty() {
+        return builder.strings([open, close]);
+    }
+
+    let mut documents = vec![soft_line()];
+
+    for (index, element) in elements.into_iter().enumerate() {
+        if index > 0 {
+            documents.extend([",".into(), line()]);
+        }
+
+        documents.push(element);
+    }
+
+    documents.push(if_break(&Document::String(","), &Document::String("")));
+
+    builder.group(builder.sequence([
+        open.into(),
+        builder.indent(builder.sequence(documents)),
+        soft_line(),
+        close.into(),
+    ]))
+}
+
+fn quote<'a, A: Allocator + Clone + 'a>(builder: &Builder<A>, string: &str) -> Document<'a> {
+    builder.strings([format!("{string:?}").as_str()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        FormatOptions, align, r#break, cache, cursor, fill, flatten, format, group, hard_line,
+        indent, line_suffix, line_suffix_boundary, mark, offside, sequence, table,
+    };
+    use alloc::string::String;
+    use bumpalo::Bump;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn dump_to_string(document: &Document, max_width: usize) -> String {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut string = String::new();
+
+        format(
+            &dump(&builder, document),
+            &mut string,
+            FormatOptions::new(2).set_max_width(max_width),
+        )
+        .unwrap();
+
+        string
+    }
+
+    #[test]
+    fn dump_leaves() {
+        for (document, string) in [
+            ("foo".into(), r#""foo""#),
+            ("\"\n".into(), r#""\"\n""#),
+            (line(), "line"),
+            (soft_line(), "soft_line"),
+            (hard_line(), "hard_line"),
+            (cursor(), "cursor"),
+            (mark(42), "mark(42)"),
+            (line_suffix("// foo"), r#"line_suffix("// foo")"#),
+            (line_suffix_boundary(), "line_suffix_boundary"),
+        ] {
+            assert_eq!(dump_to_string(&document, 80), string);
+        }
+    }
+
+    #[test]
+    fn dump_flat_document() {
+        assert_eq!(
+            dump_to_string(&group(&indent(&sequence(&[line(), "foo".into()]))), 80),
+            r#"group(indent([line, "foo"]))"#
+        );
+    }
+
+    #[test]
+    fn dump_builder_documents() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+
+        assert_eq!(
+            dump_to_string(
+                &builder.group(builder.indent(builder.sequence([line(), "foo".into()]))),
+                80
+            ),
+            r#"group(indent([line, "foo"]))"#
+        );
+        assert_eq!(
+            dump_to_string(&builder.flatten_if(true, "foo"), 80),
+            r#"flatten("foo")"#
+        );
+        assert_eq!(dump_to_string(&cache(&hard_line()), 80), "hard_line");
+    }
+
+    #[test]
+    fn dump_nested_documents() {
+        assert_eq!(
+            dump_to_string(
+                &sequence(&[
+                    r#break(&"foo".into()),
+                    flatten(&"bar".into()),
+                    Document::Cache {
+                        broken: true,
+                        document: &"baz".into(),
+                    },
+                    offside(&"qux".into(), true),
+                    if_break(&",".into(), &"".into()),
+                    sequence(&[]),
+                    align(3, &"quux".into()),
+                ]),
+                120
+            ),
+            r#"[break("foo"), flatten("bar"), cache("baz"), offside("qux", true), if_break(",", ""), [], align(3, "quux")]"#
+        );
+    }
+
+    #[test]
+    fn dump_table() {
+        assert_eq!(
+            dump_to_string(
+                &table(
+                    &[&["foo".into(), "bar".into()], &["baz".into()]],
+                    &[ColumnAlignment::Right]
+                ),
+                80
+            ),
+            r#"table([["foo", "bar"], ["baz"]], [right])"#
+        );
+    }
+
+    #[test]
+    fn dump_fill() {
+        assert_eq!(
+            dump_to_string(&fill(&["foo".into(), line(), "bar".into()]), 80),
+            r#"fill(["foo", line, "bar"])"#
+        );
+    }
+
+    #[test]
+    fn dump_broken_document() {
+        assert_eq!(
+            dump_to_string(
+                &group(&sequence(&[
+                    "{".into(),
+                    indent(&sequence(&[line(), "foo".into(), line(), "bar".into()])),
+                    line(),
+                    "}".into(),
+                ])),
+                40
+            ),
+            indoc!(
+                r#"
+                group(
+                  [
+                    "{",
+                    indent([line, "foo", line, "bar"]),
+                    line,
+                    "}",
+                  ],
+                )
+                "#
+            )
+            .trim()
+        );
+    }
+
+    #[test]
+    fn dump_deep_document() {
+        let allocator = Bump::new();
+        let builder = Builder::new(&allocator);
+        let mut document = "foo".into();
+
+        for _ in 0..100_000 {
+            document = builder.indent(document);
+        }
+
+        assert!(dump_to_string(&document, usize::MAX).starts_with("indent(indent("));
+    }
+}
@@ -0,0 +1,382 @@
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::ops::Range;
+
+/// A text edit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit<'a> {
+    range: Range<usize>,
+    replacement: &'a str,
+}
+
+impl<'a> TextEdit<'a> {
+    /// Creates a text edit.
+    pub const fn new(range: Range<usize>, replacement: &'a str) -> Self {
+        Self { range, replacement }
+    }
+
+    /// Returns a byte range in an original text.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns a replacement.
+    pub const fn replacement(&self) -> &'a str {
+        self.replacement
+    }
+}
+
+/// Computes minimal edits from an original text to a formatted one.
+///
+/// It compares texts line by line and then shrinks each edit by characters
+/// common at its both ends. Edits are sorted by their ranges and do not
+/// overlap with each other.
+pub fn diff<'a>(original: &str, formatted: &'a str) -> Vec<TextEdit<'a>> {
+    let original_lines = original.split_inclusive('\n').collect::<Vec<_>>();
+    let formatted_lines = formatted.split_inclusive('\n').collect::<Vec<_>>();
+    let original_offsets = offsets(&original_lines);
+    let formatted_offsets = offsets(&formatted_lines);
+
+    diff_lines(&original_lines, &formatted_lines)
+        .into_iter()
+        .map(|(original_range, formatted_range)| {
+            let original_range =
+                original_offsets[original_range.start]..original_offsets[original_range.end];
+            let formatted_range =
+                formatted_offsets[formatted_range.start]..formatted_offsets[formatted_range.end];
+            let original = &original[original_range.clone()];
+            let formatted = &formatted[formatted_range.clone()];
+
+            let prefix = common_prefix(original, formatted);
+            let suffix = common_suffix(&original[prefix..], &formatted[prefix..]);
+
+            TextEdit::new(
+                original_range.start + prefix..original_range.end - suffix,
+                &formatted[prefix..formatted.len() - suffix],
+            )
+        })
+        .collect()
+}
+
+fn offsets(lines: &[&str]) -> Vec<usize> {
+    let mut offsets = vec![0];
+
+    for line in lines {
+        offsets.push(offsets[offsets.len() - 1] + line.len());
+    }
+
+    offsets
+}
+
+// Computes pairs of different line ranges.
+//
+// Lines not found in the other text are never common. So it excludes them
+// before computing common lines.
+fn diff_lines(original: &[&str], formatted: &[&str]) -> Vec<(Range<usize>, Range<usize>)> {
+    let original_indices = common_indices(original, formatted);
+    let formatted_indices = common_indices(formatted, original);
+    let ranges = diff_common_lines(
+        &original_indices
+            .iter()
+            .map(|&index| original[index])
+            .collect::<Vec<_>>(),
+        &formatted_indices
+            .iter()
+            .map(|&index| formatted[index])
+            .collect::<Vec<_>>(),
+    );
+
+    // Find pairs of indices of common lines in the whole texts.
+    let mut pairs = vec![];
+    let (mut x, mut y) = (0, 0);
+
+    for (xs, ys) in ranges {
+        pairs.extend((x..xs.start).zip(y..ys.start));
+        (x, y) = (xs.end, ys.end);
+    }
+
+    pairs.extend((x..original_indices.len()).zip(y..formatted_indices.len()));
+
+    let pairs = pairs
+        .into_iter()
+        .map(|(x, y)| (original_indices[x], formatted_indices[y]))
+        .chain([(original.len(), formatted.len())]);
+
+    let mut ranges = vec![];
+    let (mut x, mut y) = (0, 0);
+
+    for (end_x, end_y) in pairs {
+        if x < end_x || y < end_y {
+            ranges.push((x..end_x, y..end_y));
+        }
+
+        (x, y) = (end_x + 1, end_y + 1);
+    }
+
+    ranges
+}
+
+fn common_indices(lines: &[&str], other_lines: &[&str]) -> Vec<usize> {
+    let other_lines = other_lines.iter().collect::<BTreeSet<_>>();
+
+    (0..lines.len())
+        .filter(|&index| other_lines.contains(&lines[index]))
+        .collect()
+}
+
+// Computes pairs of different line ranges by the linear space variant of the
+// Myers' algorithm.
+fn diff_common_lines(original: &[&str], formatted: &[&str]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut ranges = Vec::<(Range<usize>, Range<usize>)>::new();
+    let mut tasks = vec![(0..original.len(), 0..formatted.len())];
+
+    while let Some((mut xs, mut ys)) = tasks.pop() {
+        // Skip common lines at both ends.
+        while !xs.is_empty() && !ys.is_empty() && original[xs.start] == formatted[ys.start] {
+            xs.start += 1;
+            ys.start += 1;
+        }
+
+        while !xs.is_empty() && !ys.is_empty() && original[xs.end - 1] == formatted[ys.end - 1] {
+            xs.end -= 1;
+            ys.end -= 1;
+        }
+
+        if xs.is_empty() && ys.is_empty() {
+            continue;
+        } else if xs.is_empty() || ys.is_empty() {
+            match ranges.last_mut() {
+                Some((original, formatted))
+                    if original.end == xs.start && formatted.end == ys.start =>
+                {
+                    original.end = xs.end;
+                    formatted.end = ys.end;
+                }
+                _ => ranges.push((xs, ys)),
+            }
+
+            continue;
+        }
+
+        // Both sides have at least two edits here as they have no common lines
+        // at their ends. So each half has less edits than the whole.
+        let (x, y, u, v) = middle_snake(&original[xs.clone()], &formatted[ys.clone()]);
+
+        tasks.push((xs.start + u..xs.end, ys.start + v..ys.end));
+        tasks.push((xs.start..xs.start + x, ys.start..ys.start + y));
+    }
+
+    ranges
+}
+
+// Finds a middle snake on one of the shortest edit paths.
+//
+// It returns start and end points of the snake.
+fn middle_snake(original: &[&str], formatted: &[&str]) -> (usize, usize, usize, usize) {
+    let (n, m) = (original.len() as isize, formatted.len() as isize);
+    let size = 2 * n.min(m) + 2;
+    let index = |diagonal: isize| diagonal.rem_euclid(size) as usize;
+    let mut forward = vec![0; size as usize];
+    let mut backward = vec![0; size as usize];
+
+    for distance in 0..=(n + m + 1) / 2 {
+        for is_forward in [true, false] {
+            let (furthest, other) = if is_forward {
+                (&mut forward, &backward)
+            } else {
+                (&mut backward, &forward)
+            };
+
+            for diagonal in (-(distance - 2 * (distance - m).max(0))
+                ..=distance - 2 * (distance - n).max(0))
+                .step_by(2)
+            {
+                let mut x = if diagonal == -distance
+                    || diagonal != distance
+                        && furthest[index(diagonal - 1)] < furthest[index(diagonal + 1)]
+                {
+                    furthest[index(diagonal + 1)]
+                } else {
+                    furthest[index(diagonal - 1)] + 1
+                };
+                let mut y = x - diagonal;
+                let (start_x, start_y) = (x, y);
+
+                while x < n
+                    && y < m
+                    && if is_forward {
+                        original[x as usize] == formatted[y as usize]
+                    } else {
+                        original[(n - x - 1) as usize] == formatted[(m - y - 1) as usize]
+                    }
+                {
+                    x += 1;
+                    y += 1;
+                }
+
+                furthest[index(diagonal)] = x;
+
+                let other_diagonal = n - m - diagonal;
+                let other_distance = distance - if is_forward { 1 } else { 0 };
+
+                if (n + m) % 2 == if is_forward { 1 } else { 0 }
+                    && (-other_distance..=other_distance).contains(&other_diagonal)
+                    && x + other[index(other_diagonal)] >= n
+                {
+                    let (x, y, u, v) = if is_forward {
+                        (start_x, start_y, x, y)
+                    } else {
+                        (n - x, m - y, n - start_x, m - start_y)
+                    };
+
+                    return (x as usize, y as usize, u as usize, v as usize);
+                }
+            }
+        }
+    }
+
+    unreachable!("middle snake")
+}
+
+fn common_prefix(one: &str, other: &str) -> usize {
+    one.char_indices()
+        .zip(other.chars())
+        .find(|((_, one), other)| one != other)
+        .map_or(one.len().min(other.len()), |((index, _), _)| index)
+}
+
+fn common_suffix(one: &str, other: &str) -> usize {
+    one.char_indices()
+        .rev()
+        .zip(other.chars().rev())
+        .find(|((_, one), other)| one != other)
+        .map_or(one.len().min(other.len()), |((index, character), _)| {
+            one.len() - index - character.len_utf8()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{format, string::String};
+    use pretty_assertions::assert_eq;
+
+    fn apply(original: &str, edits: &[TextEdit]) -> String {
+        let mut string = String::new();
+        let mut offset = 0;
+
+        for edit in edits {
+            string.push_str(&original[offset..edit.range().start]);
+            string.push_str(edit.replacement());
+            offset = edit.range().end;
+        }
+
+        string.push_str(&original[offset..]);
+
+        string
+    }
+
+    #[test]
+    fn diff_same_texts() {
+        assert_eq!(diff("", ""), []);
+        assert_eq!(diff("foo\nbar\n", "foo\nbar\n"), []);
+    }
+
+    #[test]
+    fn diff_line() {
+        assert_eq!(
+            diff("foo\n  bar\nbaz\n", "foo\nbar\nbaz\n"),
+            [TextEdit::new(4..6, "")]
+        );
+    }
+
+    #[test]
+    fn diff_inserted_line() {
+        assert_eq!(
+            diff("foo\nbaz\n", "foo\nbar\nbaz\n"),
+            [TextEdit::new(4..4, "bar\n")]
+        );
+    }
+
+    #[test]
+    fn diff_deleted_line() {
+        assert_eq!(
+            diff("foo\n\n\nbar\n", "foo\n\nbar\n"),
+            [TextEdit::new(5..6, "")]
+        );
+    }
+
+    #[test]
+    fn diff_separate_lines() {
+        assert_eq!(
+            diff("a\nfoo \nb\nc\nbar \nd\n", "a\nfoo\nb\nc\nbar\nd\n"),
+            [TextEdit::new(5..6, ""), TextEdit::new(14..15, "")]
+        );
+    }
+
+    #[test]
+    fn diff_multi_byte_characters() {
+        assert_eq!(diff("日本\n", "日本語\n"), [TextEdit::new(6..6, "語")]);
+        assert_eq!(diff("あい\n", "あう\n"), [TextEdit::new(3..6, "う")]);
+    }
+
+    #[test]
+    fn diff_without_trailing_newline() {
+        assert_eq!(diff("foo", "foo\n"), [TextEdit::new(3..3, "\n")]);
+        assert_eq!(diff("foo\n", "foo"), [TextEdit::new(3..4, "")]);
+    }
+
+    #[test]
+    fn diff_many_lines() {
+        let original = (0..8000)
+            .map(|index| format!("  {index}\n"))
+            .collect::<String>();
+        let formatted = (0..8000)
+            .map(|index| format!("    {index}\n"))
+            .collect::<String>();
+        let edits = diff(&original, &formatted);
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(apply(&original, &edits), formatted);
+    }
+
+    #[test]
+    fn diff_many_separate_lines() {
+        let original = (0..8000)
+            .map(|index| format!("  {index}\n}}\n"))
+            .collect::<String>();
+        let formatted = (0..8000)
+            .map(|index| format!("    {index}\n}}\n"))
+            .collect::<String>();
+        let edits = diff(&original, &formatted);
+
+        assert_eq!(edits.len(), 8000);
+        assert_eq!(edits[0], TextEdit::new(2..2, "  "));
+        assert_eq!(apply(&original, &edits), formatted);
+    }
+
+    #[test]
+    fn diff_many_lines_with_common_lines() {
+        let original = (0..4000)
+            .map(|index| format!("{}{index}\n", if index % 3 == 0 { "  " } else { "" }))
+            .collect::<String>();
+        let formatted = (0..4000)
+            .filter(|index| index % 5 != 0)
+            .map(|index| format!("{}{index}\n", if index % 2 == 0 { "  " } else { "" }))
+            .collect::<String>();
+
+        assert_eq!(apply(&original, &diff(&original, &formatted)), formatted);
+    }
+
+    #[test]
+    fn apply_edits() {
+        for (original, formatted) in [
+            ("", "foo\n"),
+            ("foo\n", ""),
+            ("a\nb\nc\nd\n", "b\na\nd\nc\n"),
+            ("fn f(){\nx;\n  y;\n}\n", "fn f() {\n    x;\n    y;\n}\n"),
+            ("a\nb\nc\n", "x\nb\ny\nz\n"),
+        ] {
+            assert_eq!(apply(original, &diff(original, formatted)), formatted);
+        }
+    }
+}
@@ -5,4 +5,4 @@ set -e
 rustup component add llvm-tools-preview
 
 cargo install cargo-llvm-cov
-cargo llvm-cov --workspace --lcov --output-path lcov.info
+cargo llvm-cov --workspace --all-features --lcov --output-path lcov.info